@@ -0,0 +1,295 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use toml_edit::{Document, Item, Table};
+
+use crate::cli::table::print_table;
+use crate::settings::{check_value, find_key, Kind, Settings, KEYS};
+
+#[derive(Parser, Debug)]
+pub struct ConfigCommand {
+    #[command(subcommand)]
+    command: Option<ConfigCommands>,
+}
+
+impl ConfigCommand {
+    pub fn run(&self, settings: &Settings) -> anyhow::Result<()> {
+        match &self.command {
+            None => print_keys(settings),
+            Some(ConfigCommands::List(cmd)) => {
+                for key in KEYS {
+                    let Some(value) = settings.value(key.name) else {
+                        continue;
+                    };
+
+                    if cmd.show_origin {
+                        println!("{} = {value}  # {}", key.name, settings.origin(key.name));
+                    } else {
+                        println!("{} = {value}", key.name);
+                    }
+                }
+            }
+            Some(ConfigCommands::Get(cmd)) => {
+                let key = find_key(&cmd.key)?;
+                match settings.value(key.name) {
+                    Some(value) => match value.as_str() {
+                        Some(value) => println!("{value}"),
+                        None => println!("{value}"),
+                    },
+                    None => anyhow::bail!("`{}` is not set", key.name),
+                }
+            }
+            Some(ConfigCommands::Set(cmd)) => {
+                let key = find_key(&cmd.key)?;
+                if cmd.project && key.name == "project-root" {
+                    anyhow::bail!(
+                        "`project-root` only applies in the global config, not the project's"
+                    );
+                }
+                let value = parse_value(key.name, key.kind, &cmd.values)?;
+
+                let file = ConfigFile::open(settings, cmd.project)?;
+                let mut document = file.read()?;
+                let (table, name) = file.table_mut(&mut document, key.name);
+                table.insert(name, value);
+                file.write(&document)?;
+            }
+            Some(ConfigCommands::Unset(cmd)) => {
+                let key = find_key(&cmd.key)?;
+
+                let file = ConfigFile::open(settings, cmd.project)?;
+                let mut document = file.read()?;
+                let (table, name) = file.table_mut(&mut document, key.name);
+                if table.remove(name).is_none() {
+                    anyhow::bail!(
+                        "`{}` is not set in {}",
+                        key.name,
+                        file.path.to_string_lossy()
+                    );
+                }
+                file.write(&document)?;
+            }
+            Some(ConfigCommands::Edit(cmd)) => {
+                let file = ConfigFile::open(settings, cmd.project)?;
+                edit(&file.path)?;
+
+                // Report a broken file now, rather than on the next command.
+                file.read()?;
+            }
+            Some(ConfigCommands::Path(cmd)) => {
+                let file = ConfigFile::open(settings, cmd.project)?;
+                println!("{}", file.path.to_string_lossy());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    List(ConfigList),
+    Get(ConfigGet),
+    Set(ConfigSet),
+    Unset(ConfigUnset),
+    Edit(ConfigFileArgs),
+    Path(ConfigFileArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct ConfigList {
+    #[arg(long)]
+    show_origin: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct ConfigGet {
+    key: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct ConfigSet {
+    key: String,
+
+    /// The value; list settings accept several.
+    #[arg(required = true)]
+    values: Vec<String>,
+
+    #[arg(long)]
+    project: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct ConfigUnset {
+    key: String,
+
+    #[arg(long)]
+    project: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct ConfigFileArgs {
+    #[arg(long)]
+    project: bool,
+}
+
+/// The file settings are written to: the global config, or with `--project`,
+/// the `[tool.prp]` table of the project's `pyproject.toml`.
+struct ConfigFile {
+    path: PathBuf,
+    header: Vec<String>,
+}
+
+impl ConfigFile {
+    fn open(settings: &Settings, project: bool) -> anyhow::Result<Self> {
+        if !project {
+            return Ok(Self {
+                path: settings.config_file.clone(),
+                header: vec![],
+            });
+        }
+
+        let path = settings.project_dir.join("pyproject.toml");
+        if !path.exists() {
+            anyhow::bail!("{} does not exist", path.to_string_lossy());
+        }
+        Ok(Self {
+            path,
+            header: vec!["tool".to_string(), settings.name.clone()],
+        })
+    }
+
+    fn read(&self) -> anyhow::Result<Document> {
+        if !self.path.exists() {
+            return Ok(Document::new());
+        }
+
+        std::fs::read_to_string(&self.path)?
+            .parse()
+            .with_context(|| format!("Failed to parse {}", self.path.to_string_lossy()))
+    }
+
+    fn write(&self, document: &Document) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, document.to_string())?;
+        Ok(())
+    }
+
+    /// The table which holds `key` (which may be dotted, i.e.
+    /// `hooks.post-create`), along with the key's name within that table.
+    fn table_mut<'a, 'k>(
+        &self,
+        document: &'a mut Document,
+        key: &'k str,
+    ) -> (&'a mut Table, &'k str) {
+        let mut parts: Vec<&str> = self.header.iter().map(String::as_str).collect();
+        let (parents, name) = match key.rsplit_once('.') {
+            Some((parents, name)) => {
+                parts.extend(parents.split('.'));
+                (parts, name)
+            }
+            None => (parts, key),
+        };
+
+        let mut table = document.as_table_mut();
+        for part in parents {
+            let item = table.entry(part).or_insert_with(|| {
+                let mut table = Table::new();
+                table.set_implicit(true);
+                Item::Table(table)
+            });
+            if !item.is_table() {
+                *item = Item::Table(Table::new());
+            }
+            table = item.as_table_mut().expect("is a table");
+        }
+        (table, name)
+    }
+}
+
+/// Parses command line values as the setting's type, rejecting invalid ones.
+fn parse_value(name: &str, kind: Kind, values: &[String]) -> anyhow::Result<Item> {
+    let item = if kind == Kind::List {
+        toml_edit::value(values.iter().collect::<toml_edit::Array>())
+    } else {
+        let [value] = values else {
+            anyhow::bail!("`{name}` takes a single value");
+        };
+
+        // Values which don't parse are left as strings, to be reported below.
+        match kind {
+            Kind::Bool => match value.as_str() {
+                "true" => toml_edit::value(true),
+                "false" => toml_edit::value(false),
+                _ => toml_edit::value(value),
+            },
+            Kind::Integer => match value.parse::<u64>() {
+                Ok(integer) => toml_edit::value(integer as i64),
+                Err(_) => toml_edit::value(value),
+            },
+            _ => toml_edit::value(value),
+        }
+    };
+
+    if let Err(expected) = check_value(name, &item) {
+        anyhow::bail!("`{name}` must be {expected}, not {:?}", values.join(" "));
+    }
+    Ok(item)
+}
+
+/// Opens `path` in the user's editor.
+fn edit(path: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or("vi".to_string());
+
+    // The editor may be a command with arguments, i.e. `code --wait`.
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().context("No editor configured")?;
+
+    let stderr = os_pipe::dup_stderr()?;
+    let status = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .stdout(stderr)
+        .status()
+        .with_context(|| format!("Failed to run {editor}"))?;
+
+    if !status.success() {
+        anyhow::bail!("{editor} exited with {status}");
+    }
+    Ok(())
+}
+
+fn print_keys(settings: &Settings) {
+    eprintln!("Config file: {}", settings.config_file.to_string_lossy());
+    eprintln!();
+
+    let rows: Vec<[String; 4]> = KEYS
+        .iter()
+        .map(|key| {
+            let kind = match key.kind {
+                Kind::String => "string".to_string(),
+                Kind::Bool => "bool".to_string(),
+                Kind::Integer => "integer".to_string(),
+                Kind::List => "list".to_string(),
+                Kind::Choice(values) => values.join("|"),
+            };
+            [
+                key.name.to_string(),
+                kind,
+                key.env_var(&settings.name),
+                key.doc.to_string(),
+            ]
+        })
+        .collect();
+
+    print_table(&["Key", "Type", "Env Var", "Description"], &rows);
+}
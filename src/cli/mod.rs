@@ -1,16 +1,23 @@
 use std::io::Write;
-use std::path::PathBuf;
 use std::str::FromStr;
 
 use clap::{ArgAction, CommandFactory, Parser, Subcommand};
 use clap_complete::generate;
 
+mod config;
+mod python;
+mod table;
+mod venv;
 mod x;
 
+use crate::cli::config::ConfigCommand;
+use crate::cli::python::PythonCommand;
+use crate::cli::venv::VenvCommands;
 use crate::cli::x::ExecutableCommand;
-use crate::settings::Settings;
-use crate::shell::Shell;
-use crate::venv::Venv;
+use crate::python::PythonRequest;
+use crate::settings::{ConfigMode, Settings};
+use crate::shell::{Shell, ShellKind};
+use crate::venv::{Venv, VenvOptions};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -18,19 +25,14 @@ struct Args {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    #[arg(
-        short,
-        long,
-        action = clap::ArgAction::Set,
-        value_parser = clap_complete::Shell::from_str,
-    )]
-    shell: Option<clap_complete::Shell>,
+    #[arg(short, long, value_enum)]
+    shell: Option<ShellKind>,
 
     #[arg(short, long)]
     name: Option<String>,
 
-    #[arg(short, long)]
-    python: Option<PathBuf>,
+    #[arg(short, long, value_parser = PythonRequest::from_str)]
+    python: Option<PythonRequest>,
 }
 
 impl Args {
@@ -52,9 +54,12 @@ enum Commands {
     // New
     Activate,
     // Build(RunCommand),
+    Config(ConfigCommand),
+    Deactivate,
     Exec(ExecCommand),
     Info,
     Prompt,
+    Python(PythonCommand),
     Run(RunCommand),
     Shell(ShellCommand),
     Venv(VenvCommand),
@@ -70,6 +75,9 @@ enum Commands {
 
 #[derive(Parser, Debug)]
 pub struct VenvCommand {
+    #[command(subcommand)]
+    command: Option<VenvCommands>,
+
     #[arg(long = "no-activate", action = ArgAction::SetFalse)]
     activate: bool,
 
@@ -78,14 +86,53 @@ pub struct VenvCommand {
 
     #[arg(long)]
     fix: bool,
+
+    #[arg(long)]
+    clear: bool,
+
+    #[arg(long)]
+    system_site_packages: bool,
+
+    #[arg(long)]
+    prompt: Option<String>,
+
+    #[arg(long)]
+    copies: bool,
+
+    #[arg(long)]
+    without_pip: bool,
+
+    #[arg(long = "no-seed", action = ArgAction::SetFalse)]
+    seed: bool,
+}
+
+impl VenvCommand {
+    fn options(&self) -> VenvOptions {
+        VenvOptions {
+            fix: self.fix,
+            clear: self.clear,
+            system_site_packages: self.system_site_packages,
+            prompt: self.prompt.clone(),
+            copies: self.copies,
+            without_pip: self.without_pip,
+            seed: self.seed,
+        }
+    }
 }
 
 impl Default for VenvCommand {
     fn default() -> Self {
         Self {
+            command: None,
             activate: true,
             delete: false,
             fix: false,
+            clear: false,
+            system_site_packages: false,
+            prompt: None,
+            copies: false,
+            without_pip: false,
+            seed: true,
         }
     }
 }
@@ -114,6 +161,8 @@ pub struct ShellCommand {
 enum ShellCommands {
     Init,
     Completions,
+    /// Prints code which shows the active venv in the shell's prompt.
+    Prompt,
 }
 
 pub fn main() -> anyhow::Result<()> {
@@ -121,33 +170,62 @@ pub fn main() -> anyhow::Result<()> {
     let mut cli_command = Args::command();
     let cli_name = cli_command.get_name().to_string();
 
-    let mut settings = Settings::read(&cli_name)?;
-    settings.set_python_path(args.python);
+    // `prp config` must work even when the config is invalid, to fix it.
+    let mode = matches!(args.command, Some(Commands::Config(_))).then_some(ConfigMode::Lenient);
+    let mut settings = Settings::read(&cli_name, mode)?;
+    settings.set_python(args.python);
     settings.set_venv_name(args.name);
 
     let command = args
         .command
         .unwrap_or(Commands::Venv(VenvCommand::default()));
 
-    let clap_shell = get_shell(args.shell);
-    let shell_name = format!("{}", clap_shell);
-    let shell = Shell::new(&shell_name)?;
+    let shell_kind = get_shell(args.shell);
+    let shell = Shell::new(shell_kind)?;
 
     if let Commands::Executable(cmd) = command {
         cmd.run(&settings)?;
+    } else if let Commands::Python(cmd) = command {
+        cmd.run(&settings)?;
+    } else if let Commands::Config(cmd) = command {
+        cmd.run(&settings)?;
+    } else if let Commands::Deactivate = command {
+        shell.deactivate()?;
+    } else if let Commands::Venv(VenvCommand {
+        command: Some(subcmd),
+        ..
+    }) = command
+    {
+        subcmd.run(&settings)?;
     } else {
         let mut venv = Venv::from_current_dir(&settings)?;
 
+        // Only commands which run something in the venv (or `prp venv`) count
+        // as using it, for `prp venv gc`; not those which merely describe it or
+        // set up the shell.
+        let uses_venv = match &command {
+            Commands::Activate | Commands::Run(_) | Commands::Exec(_) => true,
+            Commands::Shell(subcmd) => subcmd.command.is_none(),
+            _ => false,
+        };
+        if uses_venv {
+            venv.mark_used();
+        }
+
         match command {
             Commands::Info => venv.print_info(),
-            Commands::Activate => shell.activate(&venv),
+            Commands::Activate => shell.activate(&venv)?,
             Commands::Run(cmd) => shell.run(&venv, cmd.command.as_deref(), &cmd.args)?,
             Commands::Exec(cmd) => shell.exec(&venv, &cmd.command, &cmd.args)?,
             Commands::Prompt => shell.prompt(&venv)?,
             Commands::Shell(subcmd) => match subcmd.command {
                 None => shell.enter(&venv)?,
-                Some(ShellCommands::Init) => shell.init(),
+                Some(ShellCommands::Init) => shell.init()?,
+                Some(ShellCommands::Prompt) => shell.init_prompt()?,
                 Some(ShellCommands::Completions) => {
+                    let Some(clap_shell) = shell_kind.completions() else {
+                        anyhow::bail!("Completions are not supported for {shell_kind}");
+                    };
                     generate(
                         clap_shell,
                         &mut cli_command,
@@ -157,22 +235,30 @@ pub fn main() -> anyhow::Result<()> {
                 }
             },
             Commands::Venv(cmd) => {
+                let _lock = venv.lock()?;
                 if cmd.delete {
                     venv.delete()?;
                 } else {
-                    venv.create(cmd.fix)?;
-                    if cmd.activate && settings.auto_activate {
-                        shell.activate(&venv);
+                    venv.create(&cmd.options())?;
+                    venv.mark_used();
+
+                    if cmd.activate && settings.auto_activate && shell.is_integrated() {
+                        shell.activate(&venv)?;
                     }
                 }
             } // Commands::Install => shell.run(&venv, "pip"),
-            Commands::Executable(_) => unreachable!(),
+            Commands::Executable(_)
+            | Commands::Python(_)
+            | Commands::Config(_)
+            | Commands::Deactivate => {
+                unreachable!()
+            }
         }
     }
 
     Ok(())
 }
 
-fn get_shell(shell: Option<clap_complete::Shell>) -> clap_complete::Shell {
-    shell.unwrap_or(clap_complete::Shell::from_env().unwrap_or(clap_complete::Shell::Bash))
+fn get_shell(shell: Option<ShellKind>) -> ShellKind {
+    shell.unwrap_or(ShellKind::from_env().unwrap_or(ShellKind::Bash))
 }
@@ -0,0 +1,127 @@
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+
+use crate::cli::table::print_table;
+use crate::python::install::{install, mirror_url, uninstall};
+use crate::python::{discover, manager, PythonRequest};
+use crate::settings::Settings;
+
+#[derive(Parser, Debug)]
+pub struct PythonCommand {
+    #[command(subcommand)]
+    command: PythonCommands,
+}
+
+impl PythonCommand {
+    pub fn run(&self, settings: &Settings) -> anyhow::Result<()> {
+        match &self.command {
+            PythonCommands::List(cmd) => {
+                let pythons: Vec<PythonInfo> = discover(&settings.python_search_dirs())
+                    .iter()
+                    .map(|python| PythonInfo {
+                        path: python.exe_path.to_string_lossy().to_string(),
+                        implementation: python.implementation.clone(),
+                        version: python.version(),
+                        arch: python.arch.clone(),
+                        venv: python.is_venv,
+                        manager: manager(python, &settings.pythons_path).to_string(),
+                    })
+                    .collect();
+
+                if cmd.json {
+                    println!("{}", serde_json::to_string_pretty(&pythons)?);
+                } else {
+                    print_pythons(&pythons);
+                }
+            }
+            PythonCommands::Install(cmd) => {
+                let request: PythonRequest = cmd.version.parse()?;
+                let source = match (&cmd.from, &settings.python_mirror) {
+                    (Some(source), _) => source.clone(),
+                    (None, Some(mirror)) => mirror_url(mirror, &cmd.version),
+                    (None, None) => anyhow::bail!(
+                        "No source for python {}, use `--from` or the `python-mirror` setting",
+                        cmd.version
+                    ),
+                };
+
+                let python = install(&settings.pythons_path, &request, &source)?;
+                eprintln!(
+                    "Installed {} {} to {}",
+                    python.implementation,
+                    python.version(),
+                    python.exe_path.to_string_lossy()
+                );
+            }
+            PythonCommands::Uninstall(cmd) => {
+                let request: PythonRequest = cmd.version.parse()?;
+                for path in uninstall(&settings.pythons_path, &request)? {
+                    eprintln!("Removed {}", path.to_string_lossy());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PythonCommands {
+    List(PythonList),
+    Install(PythonInstall),
+    Uninstall(PythonUninstall),
+}
+
+#[derive(Parser, Debug)]
+pub struct PythonList {
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct PythonInstall {
+    version: String,
+
+    #[arg(long)]
+    from: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct PythonUninstall {
+    version: String,
+}
+
+#[derive(Serialize)]
+struct PythonInfo {
+    path: String,
+    implementation: String,
+    version: String,
+    arch: String,
+    venv: bool,
+    manager: String,
+}
+
+fn print_pythons(pythons: &[PythonInfo]) {
+    let header = [
+        "Path",
+        "Implementation",
+        "Version",
+        "Arch",
+        "Venv",
+        "Manager",
+    ];
+    let rows: Vec<[String; 6]> = pythons
+        .iter()
+        .map(|p| {
+            [
+                p.path.clone(),
+                p.implementation.clone(),
+                p.version.clone(),
+                p.arch.clone(),
+                p.venv.to_string(),
+                p.manager.clone(),
+            ]
+        })
+        .collect();
+
+    print_table(&header, &rows);
+}
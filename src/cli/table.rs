@@ -0,0 +1,24 @@
+/// Prints `rows` beneath `header`, with each column padded to its widest value.
+pub fn print_table<const N: usize>(header: &[&str; N], rows: &[[String; N]]) {
+    let mut widths = header.map(str::len);
+    for row in rows {
+        for (width, value) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(value.len());
+        }
+    }
+
+    let format_row = |row: &[String]| {
+        row.iter()
+            .zip(widths.iter())
+            .map(|(value, width)| format!("{value:width$}"))
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    println!("{}", format_row(&header.map(String::from)));
+    for row in rows {
+        println!("{}", format_row(row));
+    }
+}
@@ -0,0 +1,313 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use toml_edit::{Document, Item, Table};
+
+use crate::cli::table::print_table;
+use crate::python::PythonRequest;
+use crate::settings::{Hook, Origin, Settings};
+use crate::venv::{find_venvs, StoredVenv, Venv, VenvOptions};
+
+#[derive(Subcommand, Debug)]
+pub enum VenvCommands {
+    List(VenvList),
+    Gc(VenvGc),
+    Use(VenvUse),
+    Clone(VenvClone),
+    Relocate(VenvRelocate),
+}
+
+impl VenvCommands {
+    pub fn run(&self, settings: &Settings) -> anyhow::Result<()> {
+        match self {
+            Self::List(cmd) if cmd.project => {
+                let current = Venv::from_current_dir(settings)?;
+                print_project_venvs(&project_venvs(settings), &current.paths.path);
+            }
+            Self::List(_) => print_venvs(&stored_venvs(settings)),
+            Self::Gc(cmd) => {
+                let max_age = cmd
+                    .days
+                    .map(|days| Duration::from_secs(days * 24 * 60 * 60));
+
+                let mut freed = 0;
+                for venv in stored_venvs(settings) {
+                    let reason = if venv.is_orphaned() {
+                        "its project no longer exists"
+                    } else if max_age.map(|a| age(&venv) > Some(a)).unwrap_or(false) {
+                        "it hasn't been used recently"
+                    } else {
+                        continue;
+                    };
+
+                    // Never a venv which another prp process is using.
+                    let name = venv.name();
+                    let _lock = match Venv::at(settings, venv.path.clone(), &name).try_lock()? {
+                        Ok(lock) => lock,
+                        Err(holder) => {
+                            eprintln!(
+                                "Skipping {} (locked by process {holder})",
+                                venv.path.to_string_lossy()
+                            );
+                            continue;
+                        }
+                    };
+
+                    let size = venv.size();
+                    if cmd.dry_run {
+                        eprintln!("Would remove {} ({reason})", venv.path.to_string_lossy());
+                    } else {
+                        eprintln!("Removing {} ({reason})", venv.path.to_string_lossy());
+                        std::fs::remove_dir_all(&venv.path)?;
+                        remove_empty_parents(&venv.path, &settings.data_dir);
+                    }
+                    freed += size;
+                }
+
+                let verb = if cmd.dry_run { "Would free" } else { "Freed" };
+                eprintln!("{verb} {}", format_size(freed));
+            }
+            Self::Use(cmd) => {
+                let venv = Venv::from_current_dir_with_name(settings, &cmd.name)?;
+
+                let path = &settings.projects_file;
+                let mut document: Document = match std::fs::read_to_string(path) {
+                    Ok(content) => content
+                        .parse()
+                        .with_context(|| format!("Failed to parse {}", path.to_string_lossy()))?,
+                    Err(_) => Document::new(),
+                };
+
+                let project = settings.project_dir.to_string_lossy().to_string();
+                let table = document
+                    .entry(&project)
+                    .or_insert(Item::Table(Table::new()))
+                    .as_table_mut()
+                    .with_context(|| format!("`{project}` in {path:?} is not a table"))?;
+                table.insert("venv-name", toml_edit::value(&cmd.name));
+
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(path, document.to_string())?;
+
+                eprintln!("Using `{}` for {project}", cmd.name);
+                if !venv.exists() {
+                    eprintln!(
+                        "{} does not exist yet, create it with `prp venv`",
+                        venv.paths.path.to_string_lossy()
+                    );
+                }
+            }
+            Self::Clone(cmd) => {
+                let source = Venv::from_current_dir_with_name(settings, &cmd.from)?;
+                if !source.exists() {
+                    anyhow::bail!("{} does not exist", source.paths.path.to_string_lossy());
+                }
+
+                let mut dest = Venv::from_current_dir_with_name(settings, &cmd.to)?;
+                let _lock = dest.lock()?;
+                if dest.exists() {
+                    anyhow::bail!("{} already exists", dest.paths.path.to_string_lossy());
+                }
+
+                // Otherwise, the same python as the source.
+                let executable = source.cfg().executable();
+                match (&cmd.python, executable) {
+                    (Some(python), _) => dest.python = python.clone(),
+                    (None, _) if settings.origin("python") == Origin::Cli => {}
+                    (None, Some(executable)) => dest.python = PythonRequest::Executable(executable),
+                    (None, None) => {}
+                }
+
+                // The packages are installed with pip, regardless of the source.
+                let options = VenvOptions {
+                    without_pip: false,
+                    ..source.paths.read_options()
+                };
+                dest.create(&options)?;
+
+                let failed = dest.clone_packages(&source)?;
+                dest.run_hook(Hook::PostInstall)?;
+                if !failed.is_empty() {
+                    let version = dest.cfg().version().unwrap_or_default();
+                    eprintln!("Could not install on python {version}:");
+                    for requirement in failed {
+                        eprintln!("  {requirement}");
+                    }
+                }
+            }
+            Self::Relocate(cmd) => {
+                let venv = Venv::from_current_dir(settings)?;
+                let _lock = venv.lock()?;
+                if !venv.exists() {
+                    anyhow::bail!("{} does not exist", venv.paths.path.to_string_lossy());
+                }
+
+                let Some(from) = cmd.from.clone().or_else(|| venv.moved_from()) else {
+                    eprintln!("{} has not moved", venv.paths.path.to_string_lossy());
+                    return Ok(());
+                };
+                let rewritten = venv.relocate(&from)?;
+                eprintln!("Rewrote {} files", rewritten.len());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct VenvList {
+    /// List the current project's venvs, rather than every stored venv.
+    #[arg(long)]
+    project: bool,
+}
+
+/// Makes `name` the current project's venv whenever `-n/--name` isn't given.
+#[derive(Parser, Debug)]
+pub struct VenvUse {
+    name: String,
+}
+
+/// Creates a venv with the same packages as another.
+#[derive(Parser, Debug)]
+pub struct VenvClone {
+    from: String,
+    to: String,
+
+    #[arg(short, long, value_parser = PythonRequest::from_str)]
+    python: Option<PythonRequest>,
+}
+
+/// Rewrites a moved venv's paths for its new location.
+#[derive(Parser, Debug)]
+pub struct VenvRelocate {
+    /// The venv's previous location, if it can't be detected.
+    #[arg(long)]
+    from: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+pub struct VenvGc {
+    /// Also remove venvs which haven't been used in this many days.
+    #[arg(long)]
+    days: Option<u64>,
+
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// The venvs prp keeps under its data directory (i.e. for the `central`
+/// strategy), other than those of `prp x` executables.
+fn stored_venvs(settings: &Settings) -> Vec<StoredVenv> {
+    find_venvs(
+        &settings.data_dir,
+        &[
+            settings.pythons_path.clone(),
+            settings.executables_path.clone(),
+        ],
+    )
+}
+
+/// Every venv belonging to the current project: those in the project directory,
+/// and stored venvs created for it.
+fn project_venvs(settings: &Settings) -> Vec<StoredVenv> {
+    let mut venvs: Vec<StoredVenv> = settings
+        .project_dir
+        .read_dir()
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.join("pyvenv.cfg").is_file())
+        .map(|path| StoredVenv::read(&path))
+        .collect();
+
+    for venv in stored_venvs(settings) {
+        let is_project = venv.project.as_ref() == Some(&settings.project_dir);
+        if is_project && !venvs.iter().any(|v| v.path == venv.path) {
+            venvs.push(venv);
+        }
+    }
+
+    venvs.sort_by(|a, b| a.path.cmp(&b.path));
+    venvs
+}
+
+/// Removes the directories which only existed to hold a central venv.
+fn remove_empty_parents(path: &Path, root: &Path) {
+    for parent in path.ancestors().skip(1) {
+        if parent == root || !parent.starts_with(root) || std::fs::remove_dir(parent).is_err() {
+            break;
+        }
+    }
+}
+
+fn age(venv: &StoredVenv) -> Option<Duration> {
+    venv.last_used
+        .and_then(|t| SystemTime::now().duration_since(t).ok())
+}
+
+fn format_age(venv: &StoredVenv) -> String {
+    match age(venv).map(|a| a.as_secs() / (24 * 60 * 60)) {
+        None => "-".to_string(),
+        Some(0) => "today".to_string(),
+        Some(1) => "1 day ago".to_string(),
+        Some(days) => format!("{days} days ago"),
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KB", "MB", "GB"] {
+        if size < 1024.0 {
+            return format!("{size:.1} {unit}");
+        }
+        size /= 1024.0;
+    }
+    format!("{size:.1} TB")
+}
+
+fn print_project_venvs(venvs: &[StoredVenv], current: &Path) {
+    let header = ["", "Name", "Python", "Path"];
+    let rows: Vec<[String; 4]> = venvs
+        .iter()
+        .map(|venv| {
+            let marker = if venv.path == current { "*" } else { "" };
+            [
+                marker.to_string(),
+                venv.name(),
+                venv.python.clone().unwrap_or("-".to_string()),
+                venv.path.to_string_lossy().to_string(),
+            ]
+        })
+        .collect();
+    print_table(&header, &rows);
+}
+
+fn print_venvs(venvs: &[StoredVenv]) {
+    let header = ["Project", "Name", "Python", "Size", "Last Used"];
+    let rows: Vec<[String; 5]> = venvs
+        .iter()
+        .map(|venv| {
+            let project = match &venv.project {
+                Some(project) if venv.is_orphaned() => {
+                    format!("{} (missing)", project.to_string_lossy())
+                }
+                Some(project) => project.to_string_lossy().to_string(),
+                None => "-".to_string(),
+            };
+            [
+                project,
+                venv.name(),
+                venv.python.clone().unwrap_or("-".to_string()),
+                format_size(venv.size()),
+                format_age(venv),
+            ]
+        })
+        .collect();
+    print_table(&header, &rows);
+}
@@ -1,8 +1,8 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Parser, Subcommand};
 
 use crate::package_specifier::PackageSpecifier;
-use crate::settings::Settings;
-use crate::venv::Venv;
+use crate::settings::{Hook, Settings};
+use crate::venv::{Venv, VenvOptions};
 
 #[derive(Parser, Debug)]
 pub struct ExecutableCommand {
@@ -16,8 +16,13 @@ impl ExecutableCommand {
             ExecutableCommands::Install(cmd) => {
                 let spec = PackageSpecifier::parse(cmd.package.as_ref())?;
                 let mut venv = Venv::from_package_name(settings, &spec.name());
-                venv.create(cmd.force)?;
-                venv.install(spec)?
+                let _lock = venv.lock()?;
+                venv.create(&VenvOptions {
+                    fix: cmd.force,
+                    ..Default::default()
+                })?;
+                venv.install(spec, cmd.compile)?;
+                venv.run_hook(Hook::PostInstall)?;
                 // TODO:
                 //  * warn if binary path is not on PATH
                 //  * warn if no apps are exposed by installation
@@ -54,6 +59,9 @@ pub struct ExecutableInstall {
 
     #[arg(short, long)]
     force: bool,
+
+    #[arg(long = "no-compile", action = ArgAction::SetFalse)]
+    compile: bool,
 }
 
 #[derive(Parser, Debug)]
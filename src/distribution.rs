@@ -0,0 +1,98 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::wheel::metadata_headers;
+
+/// An installed distribution, as described by its `.dist-info` directory.
+pub struct Distribution {
+    pub name: String,
+    pub version: String,
+
+    /// Where it was installed from, if not an index.
+    pub direct_url: Option<DirectUrl>,
+}
+
+/// How a distribution was installed from a URL rather than an index, as
+/// recorded in its `direct_url.json` (PEP 610).
+#[derive(Deserialize)]
+pub struct DirectUrl {
+    pub url: String,
+    pub dir_info: Option<DirInfo>,
+    pub vcs_info: Option<VcsInfo>,
+}
+
+#[derive(Deserialize)]
+pub struct DirInfo {
+    #[serde(default)]
+    pub editable: bool,
+}
+
+#[derive(Deserialize)]
+pub struct VcsInfo {
+    pub vcs: String,
+    pub commit_id: String,
+}
+
+impl DirectUrl {
+    pub fn read(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// The local directory of an editable install.
+    pub fn editable_path(&self) -> Option<PathBuf> {
+        if !self.dir_info.as_ref().map(|d| d.editable).unwrap_or(false) {
+            return None;
+        }
+        self.url.strip_prefix("file://").map(PathBuf::from)
+    }
+}
+
+impl Distribution {
+    pub fn from_dist_info(dist_info: &Path) -> Option<Self> {
+        let metadata = dist_info.join("METADATA");
+        let header = |name| metadata_headers(&metadata, name).ok()?.into_iter().next();
+
+        Some(Self {
+            name: header("Name")?,
+            version: header("Version")?,
+            direct_url: DirectUrl::read(&dist_info.join("direct_url.json")),
+        })
+    }
+
+    /// The arguments to `pip install` which reinstall this exact distribution,
+    /// from wherever it was originally installed.
+    pub fn pip_args(&self) -> Vec<String> {
+        let Some(direct_url) = &self.direct_url else {
+            return vec![format!("{}=={}", self.name, self.version)];
+        };
+
+        if let Some(path) = direct_url.editable_path() {
+            return vec!["-e".to_string(), path.to_string_lossy().to_string()];
+        }
+
+        match &direct_url.vcs_info {
+            Some(vcs) => vec![format!(
+                "{} @ {}+{}@{}",
+                self.name, vcs.vcs, direct_url.url, vcs.commit_id
+            )],
+            None => vec![format!("{} @ {}", self.name, direct_url.url)],
+        }
+    }
+}
+
+/// Every distribution installed into the given site-packages directory.
+pub fn installed(site_packages: &Path) -> Vec<Distribution> {
+    let Ok(entries) = site_packages.read_dir() else {
+        return vec![];
+    };
+
+    let mut distributions: Vec<Distribution> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|e| e == "dist-info").unwrap_or(false))
+        .filter_map(|path| Distribution::from_dist_info(&path))
+        .collect();
+    distributions.sort_by_key(|d| d.name.to_lowercase());
+    distributions
+}
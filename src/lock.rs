@@ -0,0 +1,100 @@
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// An advisory lock (i.e. on a venv), held until dropped. The lock file holds
+/// the PID of the process holding the lock, so that others can say who
+/// they're waiting on.
+pub struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    /// Waits up to `timeout` to lock `path`, describing what's locked as
+    /// `description` in messages.
+    pub fn acquire(path: &Path, description: &str, timeout: Duration) -> anyhow::Result<Self> {
+        let mut file = open(path)?;
+
+        let start = Instant::now();
+        let mut waiting = false;
+        while !try_lock(&file)? {
+            let holder = read_pid(&mut file);
+            if start.elapsed() > timeout {
+                anyhow::bail!(
+                    "Timed out after {}s waiting for process {holder} to unlock {description}",
+                    timeout.as_secs()
+                );
+            }
+
+            if !waiting {
+                eprintln!("Waiting for process {holder} to unlock {description}");
+                waiting = true;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+
+        Self::hold(file)
+    }
+
+    /// Locks `path` if no one else holds it, or otherwise returns the PID of
+    /// the process which does.
+    pub fn try_acquire(path: &Path) -> anyhow::Result<Result<Self, String>> {
+        let mut file = open(path)?;
+        if !try_lock(&file)? {
+            return Ok(Err(read_pid(&mut file)));
+        }
+        Ok(Ok(Self::hold(file)?))
+    }
+
+    fn hold(mut file: File) -> anyhow::Result<Self> {
+        file.set_len(0)?;
+        file.rewind()?;
+        write!(file, "{}", std::process::id())?;
+        file.flush()?;
+
+        Ok(Self { _file: file })
+    }
+}
+
+fn open(path: &Path) -> anyhow::Result<File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    Ok(File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?)
+}
+
+/// Whether the (exclusive) lock was taken, rather than held by someone else.
+fn try_lock(file: &File) -> anyhow::Result<bool> {
+    // SAFETY: The descriptor is valid for the lifetime of `file`, and the lock
+    // is released when it's closed.
+    let result = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if result == 0 {
+        return Ok(true);
+    }
+
+    let error = std::io::Error::last_os_error();
+    match error.kind() {
+        std::io::ErrorKind::WouldBlock => Ok(false),
+        _ => Err(error.into()),
+    }
+}
+
+fn read_pid(file: &mut File) -> String {
+    let mut pid = String::new();
+    if file.rewind().is_err() || file.read_to_string(&mut pid).is_err() {
+        return "?".to_string();
+    }
+
+    match pid.trim() {
+        "" => "?".to_string(),
+        pid => pid.to_string(),
+    }
+}
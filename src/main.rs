@@ -1,9 +1,13 @@
 mod cli;
+mod distribution;
+mod lock;
 mod posy;
 mod python;
+mod pyvenv_cfg;
 mod settings;
 mod shell;
 mod venv;
+mod wheel;
 
 mod package_specifier;
 
@@ -1,13 +1,14 @@
 use std::fmt::Display;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use crate::posy::package_name::PackageName;
 use crate::posy::requirement::{ParseExtra, Requirement};
+use crate::wheel::Wheel;
 
 #[derive(Debug)]
 pub enum PackageSpecifier {
     Pep508Specifier(Requirement),
 
-    #[allow(dead_code)]
     LocalPackage(PathBuf),
 
     #[allow(dead_code)]
@@ -16,6 +17,12 @@ pub enum PackageSpecifier {
 
 impl PackageSpecifier {
     pub fn parse(value: &str) -> anyhow::Result<Self> {
+        let path = Path::new(value);
+        if path.extension().map(|e| e == "whl").unwrap_or(false) && path.exists() {
+            Wheel::from_path(path)?;
+            return Ok(Self::LocalPackage(path.to_path_buf()));
+        }
+
         let maybe_requirement = Requirement::parse(value, ParseExtra::Allowed);
         if let Ok(requirement) = maybe_requirement {
             return Ok(Self::Pep508Specifier(requirement));
@@ -26,6 +33,11 @@ impl PackageSpecifier {
     pub fn name(&self) -> String {
         match self {
             Self::Pep508Specifier(req) => req.name.normalized().to_string(),
+            Self::LocalPackage(path) => Wheel::from_path(path)
+                .ok()
+                .and_then(|wheel| PackageName::try_from(wheel.name.as_str()).ok())
+                .map(|name| name.normalized().to_string())
+                .unwrap_or_else(|| path.to_string_lossy().to_string()),
             _ => unimplemented!(),
         }
     }
@@ -35,6 +47,7 @@ impl Display for PackageSpecifier {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Pep508Specifier(req) => req.fmt(f),
+            Self::LocalPackage(path) => write!(f, "{}", path.to_string_lossy()),
             _ => unimplemented!(),
         }
     }
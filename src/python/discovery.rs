@@ -0,0 +1,190 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::python::{Python, PythonRequest};
+
+/// Finds the best interpreter for the request: the newest matching version
+/// (outside of any venv), preferring those found earlier in the search path.
+pub fn find_python(request: &PythonRequest, extra_dirs: &[PathBuf]) -> anyhow::Result<Python> {
+    if let PythonRequest::Executable(path) = request {
+        return Python::detect(path);
+    }
+
+    let mut pythons = discover(extra_dirs);
+
+    let mut best: Option<usize> = None;
+    for (index, python) in pythons.iter().enumerate() {
+        let is_better = best
+            .map(|b| python.version_parts() > pythons[b].version_parts())
+            .unwrap_or(true);
+        if !python.is_venv && request.matches(python) && is_better {
+            best = Some(index);
+        }
+    }
+
+    if let Some(index) = best {
+        return Ok(pythons.swap_remove(index));
+    }
+
+    let found: Vec<String> = pythons
+        .iter()
+        .map(|p| {
+            format!(
+                "  {} ({} {})",
+                p.exe_path.to_string_lossy(),
+                p.implementation,
+                p.version()
+            )
+        })
+        .collect();
+    anyhow::bail!(
+        "No python interpreter matching `{request}` was found. Found:\n{}",
+        found.join("\n")
+    )
+}
+
+/// Every distinct python interpreter in the search path, in search order.
+pub fn discover(extra_dirs: &[PathBuf]) -> Vec<Python> {
+    let candidates = candidates(&search_dirs(extra_dirs));
+
+    // Probing an interpreter means executing it, so do several at once (but no
+    // more than there are cores).
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4);
+    let mut detected: Vec<Option<Python>> = vec![];
+    for batch in candidates.chunks(workers) {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = batch
+                .iter()
+                .map(|path| scope.spawn(move || Python::detect(path).ok()))
+                .collect();
+            detected.extend(
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().ok().flatten()),
+            );
+        });
+    }
+
+    let mut seen = HashSet::new();
+    detected
+        .into_iter()
+        .flatten()
+        .filter(|python| {
+            let real_path =
+                std::fs::canonicalize(&python.exe_path).unwrap_or(python.exe_path.clone());
+            seen.insert(real_path)
+        })
+        .collect()
+}
+
+/// What installed the interpreter, judging by where it lives.
+pub fn manager(python: &Python, pythons_path: &Path) -> &'static str {
+    let path = &python.exe_path;
+    if path.starts_with(pythons_path) {
+        return "prp";
+    }
+
+    if pyenv_root().is_some_and(|root| path.starts_with(root)) {
+        return "pyenv";
+    }
+
+    if let Some(home) = std::env::var_os("HOME").map(PathBuf::from) {
+        let asdf_dir = std::env::var_os("ASDF_DATA_DIR")
+            .map(PathBuf::from)
+            .unwrap_or(home.join(".asdf"));
+        if path.starts_with(asdf_dir) {
+            return "asdf";
+        }
+    }
+
+    if ["/usr", "/bin", "/opt/homebrew"]
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+    {
+        return "system";
+    }
+    "other"
+}
+
+/// Where pyenv keeps its installations, under `versions/`.
+pub fn pyenv_root() -> Option<PathBuf> {
+    std::env::var_os("PYENV_ROOT")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".pyenv")))
+}
+
+/// Directories which may contain interpreters, in priority order.
+fn search_dirs(extra_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut dirs = extra_dirs.to_vec();
+
+    if let Some(path) = std::env::var_os("PATH") {
+        dirs.extend(std::env::split_paths(&path));
+    }
+
+    dirs.push(PathBuf::from("/usr/local/bin"));
+    dirs.push(PathBuf::from("/usr/bin"));
+
+    if let Some(pyenv_root) = pyenv_root() {
+        dirs.extend(installation_bin_dirs(&pyenv_root.join("versions")));
+    }
+
+    if let Some(home) = std::env::var_os("HOME").map(PathBuf::from) {
+        let asdf_dir = std::env::var_os("ASDF_DATA_DIR")
+            .map(PathBuf::from)
+            .unwrap_or(home.join(".asdf"));
+        dirs.extend(installation_bin_dirs(&asdf_dir.join("installs/python")));
+    }
+
+    // Shims (i.e. pyenv's) are scripts which dispatch to some other interpreter.
+    dirs.retain(|dir| !dir.ends_with("shims"));
+
+    let mut seen = HashSet::new();
+    dirs.retain(|dir| seen.insert(dir.clone()));
+    dirs
+}
+
+/// `{root}/*/bin`, for tools which keep one installation per directory.
+pub fn installation_bin_dirs(root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = root.read_dir() else {
+        return vec![];
+    };
+
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path().join("bin"))
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+fn candidates(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let re = Regex::new(r"^(python|pypy)([0-9]+(\.[0-9]+)?)?$").expect("valid regex");
+
+    let mut result = vec![];
+    let mut seen = HashSet::new();
+    for dir in dirs {
+        let Ok(entries) = dir.read_dir() else {
+            continue;
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(Result::ok)
+            .filter(|entry| re.is_match(&entry.file_name().to_string_lossy()))
+            .map(|entry| entry.path())
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let real_path = std::fs::canonicalize(&path).unwrap_or(path.clone());
+            if real_path.is_file() && seen.insert(real_path) {
+                result.push(path);
+            }
+        }
+    }
+    result
+}
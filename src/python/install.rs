@@ -0,0 +1,238 @@
+use anyhow::Context;
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use which::which;
+
+use crate::python::discovery::installation_bin_dirs;
+use crate::python::{Python, PythonRequest};
+
+/// Installs a python-build-standalone style archive (a `.tar.gz` containing a
+/// relocatable installation under a top-level `python/` directory) into
+/// `pythons_path`, where interpreter discovery will find it.
+///
+/// `source` is either a local archive path or a URL; URLs are downloaded with
+/// `curl`.
+pub fn install(
+    pythons_path: &Path,
+    request: &PythonRequest,
+    source: &str,
+) -> anyhow::Result<Python> {
+    require_version(request)?;
+    std::fs::create_dir_all(pythons_path)?;
+
+    let tmp = pythons_path.join(format!(".install.{}", std::process::id()));
+    if tmp.exists() {
+        std::fs::remove_dir_all(&tmp)?;
+    }
+    std::fs::create_dir_all(&tmp)?;
+
+    let result = install_into(pythons_path, &tmp, request, source);
+    std::fs::remove_dir_all(&tmp)?;
+    result
+}
+
+fn install_into(
+    pythons_path: &Path,
+    tmp: &Path,
+    request: &PythonRequest,
+    source: &str,
+) -> anyhow::Result<Python> {
+    let archive = if source.contains("://") {
+        let archive = tmp.join("archive.tar.gz");
+        download(source, &archive)?;
+        archive
+    } else {
+        PathBuf::from(source)
+    };
+
+    let unpacked = tmp.join("unpacked");
+    let file = File::open(&archive).with_context(|| format!("Failed to open {archive:?}"))?;
+    tar::Archive::new(GzDecoder::new(file))
+        .unpack(&unpacked)
+        .with_context(|| format!("Failed to unpack {archive:?}"))?;
+
+    let root = unpacked.join("python");
+    let python = Python::detect(&root.join("bin/python3"))
+        .with_context(|| format!("{archive:?} does not contain a `python/bin/python3`"))?;
+    if !request.matches(&python) {
+        anyhow::bail!(
+            "{archive:?} contains python {}, which does not match `{request}`",
+            python.version()
+        );
+    }
+
+    let dest = pythons_path.join(format!(
+        "{}-{}-{}",
+        python.implementation,
+        python.version(),
+        python.arch
+    ));
+    if dest.exists() {
+        anyhow::bail!(
+            "{} {} is already installed at {dest:?}",
+            python.implementation,
+            python.version()
+        );
+    }
+    std::fs::rename(&root, &dest)?;
+
+    Python::detect(&dest.join("bin/python3"))
+}
+
+/// Removes every installed python matching the request, returning their paths.
+pub fn uninstall(pythons_path: &Path, request: &PythonRequest) -> anyhow::Result<Vec<PathBuf>> {
+    require_version(request)?;
+
+    let mut removed = vec![];
+    for python in installed(pythons_path) {
+        if !request.matches(&python) {
+            continue;
+        }
+
+        let root = python
+            .exe_path
+            .parent()
+            .and_then(Path::parent)
+            .context("Unexpected installation layout")?;
+        if !root.starts_with(pythons_path) {
+            continue;
+        }
+
+        std::fs::remove_dir_all(root)?;
+        removed.push(root.to_path_buf());
+    }
+
+    if removed.is_empty() {
+        anyhow::bail!("No installed python matches `{request}`");
+    }
+    Ok(removed)
+}
+
+/// Installations are only ever chosen by version; an executable request (i.e.
+/// `python3.11`) would match every one of them.
+fn require_version(request: &PythonRequest) -> anyhow::Result<()> {
+    if let PythonRequest::Executable(_) = request {
+        anyhow::bail!("`{request}` is not a python version, i.e. `3.11` or `pypy3.10`");
+    }
+    Ok(())
+}
+
+/// Every python installed by prp.
+pub fn installed(pythons_path: &Path) -> Vec<Python> {
+    installation_bin_dirs(pythons_path)
+        .iter()
+        .filter_map(|dir| Python::detect(&dir.join("python3")).ok())
+        .collect()
+}
+
+/// The archive URL for a version, from a mirror URL template with `{version}`,
+/// `{arch}`, and `{os}` placeholders.
+pub fn mirror_url(template: &str, version: &str) -> String {
+    template
+        .replace("{version}", version)
+        .replace("{arch}", std::env::consts::ARCH)
+        .replace("{os}", std::env::consts::OS)
+}
+
+fn download(url: &str, dest: &Path) -> anyhow::Result<()> {
+    let curl = which("curl").context("Downloading python requires `curl`")?;
+    let status = Command::new(curl)
+        .args([
+            "--fail",
+            "--location",
+            "--silent",
+            "--show-error",
+            "--output",
+        ])
+        .arg(dest)
+        .arg(url)
+        .status()?;
+
+    if !status.success() {
+        anyhow::bail!("Failed to download {url}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// An empty directory for a test to work in.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("prp-{name}-{}", std::process::id()));
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A `.tar.gz` of a `python/bin/python3` which describes itself as the
+    /// given version, as `Python::detect` asks it to.
+    fn archive(dir: &Path, version: &str) -> PathBuf {
+        let stub = dir.join("python3");
+        std::fs::write(
+            &stub,
+            format!("#!/bin/sh\nprintf 'cpython\\n{version}\\nx86_64\\nFalse\\n'\n"),
+        )
+        .unwrap();
+        std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let path = dir.join(format!("cpython-{version}.tar.gz"));
+        let encoder = GzEncoder::new(File::create(&path).unwrap(), Default::default());
+        let mut builder = tar::Builder::new(encoder);
+        builder
+            .append_path_with_name(&stub, "python/bin/python3")
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn install_from_a_local_archive_and_uninstall() {
+        let dir = test_dir("install");
+        let pythons_path = dir.join("pythons");
+        let source = archive(&dir, "3.11.4");
+
+        let request = "3.11".parse().unwrap();
+        let python = install(&pythons_path, &request, &source.to_string_lossy()).unwrap();
+        let root = pythons_path.join("cpython-3.11.4-x86_64");
+        assert_eq!(python.exe_path, root.join("bin/python3"));
+        assert_eq!(installed(&pythons_path).len(), 1);
+
+        // Installing the same version again is refused, and the mismatched
+        // version of a request isn't installed.
+        assert!(install(&pythons_path, &request, &source.to_string_lossy()).is_err());
+        let request = "3.12".parse().unwrap();
+        assert!(install(&pythons_path, &request, &source.to_string_lossy()).is_err());
+
+        let request = "3.11".parse().unwrap();
+        assert_eq!(uninstall(&pythons_path, &request).unwrap(), vec![root]);
+        assert!(installed(&pythons_path).is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn executable_requests_are_rejected() {
+        let dir = test_dir("uninstall");
+        let pythons_path = dir.join("pythons");
+        let source = archive(&dir, "3.11.4");
+        let request = "3.11".parse().unwrap();
+        install(&pythons_path, &request, &source.to_string_lossy()).unwrap();
+
+        for name in ["python3.11", "python3"] {
+            let request = name.parse().unwrap();
+            assert!(uninstall(&pythons_path, &request).is_err());
+            assert!(install(&pythons_path, &request, &source.to_string_lossy()).is_err());
+        }
+        assert_eq!(installed(&pythons_path).len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use which::which;
+
+mod discovery;
+pub mod install;
+mod request;
+
+pub use discovery::{discover, find_python, installation_bin_dirs, manager, pyenv_root};
+pub use request::{satisfies, PythonRequest};
+
+/// Printed by the interpreter to describe itself; compatible with python 2.
+const DETECT_SCRIPT: &str = r#"
+import platform, sys
+print(platform.python_implementation().lower())
+print("%d.%d.%d" % sys.version_info[:3])
+print(platform.machine())
+print(sys.prefix != getattr(sys, "base_prefix", getattr(sys, "real_prefix", sys.prefix)))
+print(sys.executable)
+"#;
+
+pub struct Python {
+    pub exe_path: PathBuf,
+    pub implementation: String,
+    pub arch: String,
+    pub is_venv: bool,
+    pub major: String,
+    pub minor: String,
+    pub patch: String,
+}
+
+impl Python {
+    pub fn detect(python_path: &Path) -> anyhow::Result<Self> {
+        let path = if python_path.is_absolute() {
+            python_path.to_path_buf()
+        } else {
+            which(python_path)?
+        };
+
+        let output = Command::new(&path).args(["-c", DETECT_SCRIPT]).output()?;
+        let output = String::from_utf8(output.stdout)?;
+
+        let mut lines = output.lines();
+        let (Some(implementation), Some(version), Some(arch), Some(is_venv)) =
+            (lines.next(), lines.next(), lines.next(), lines.next())
+        else {
+            anyhow::bail!("{path:?} is not a functional python interpreter");
+        };
+
+        let mut parts = version.split('.').map(String::from);
+        let (Some(major), Some(minor), Some(patch)) = (parts.next(), parts.next(), parts.next())
+        else {
+            anyhow::bail!("{path:?} reported an unrecognized version: {version}");
+        };
+
+        // i.e. a pyenv shim is a script, which must not be confused for the
+        // interpreter it ultimately executes.
+        let exe_path = lines
+            .next()
+            .filter(|e| !e.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or(path);
+
+        Ok(Self {
+            exe_path,
+            implementation: implementation.to_string(),
+            arch: arch.to_string(),
+            is_venv: is_venv == "True",
+            major,
+            minor,
+            patch,
+        })
+    }
+
+    pub fn version(&self) -> String {
+        format!("{}.{}.{}", self.major, self.minor, self.patch)
+    }
+
+    pub fn version_parts(&self) -> Vec<u64> {
+        [&self.major, &self.minor, &self.patch]
+            .iter()
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    }
+}
@@ -0,0 +1,320 @@
+use anyhow::Context;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use toml_edit::Document;
+
+use crate::posy::specifier::{CompareOp, Specifier, Specifiers};
+use crate::python::{pyenv_root, Python};
+
+/// Which python interpreter to use, as requested by the user.
+#[derive(Clone, Debug)]
+pub enum PythonRequest {
+    /// A path, or an executable name to be found on the `PATH`, i.e. `python3`.
+    Executable(PathBuf),
+
+    /// Any interpreter matching the version constraints, i.e. `3.11`,
+    /// `pypy3.10`, or `>=3.9,<3.12`.
+    Version {
+        implementation: Option<String>,
+        specifiers: Specifiers,
+    },
+}
+
+impl PythonRequest {
+    /// The python a project asks for, through either a `.python-version` file
+    /// (as used by pyenv and others) or its `[project].requires-python`. Files
+    /// which can't be understood are warned about, and ignored.
+    pub fn from_project(project_dir: &Path) -> Option<Self> {
+        let python_version = project_dir.join(".python-version");
+        let pyproject = project_dir.join("pyproject.toml");
+        let requests = [
+            python_version
+                .exists()
+                .then(|| Self::from_python_version(&python_version)),
+            pyproject.exists().then(|| Self::from_pyproject(&pyproject)),
+        ];
+
+        for request in requests.into_iter().flatten() {
+            match request {
+                Ok(Some(request)) => return Some(request),
+                Ok(None) => {}
+                Err(e) => eprintln!("Warning: {e:#}, ignoring it"),
+            }
+        }
+        None
+    }
+
+    fn from_python_version(path: &Path) -> anyhow::Result<Option<Self>> {
+        let content = std::fs::read_to_string(path)?;
+        let Some(version) = content
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+        else {
+            return Ok(None);
+        };
+        if version == "system" {
+            return Ok(None);
+        }
+
+        // Any pyenv version name, i.e. `miniconda3-4.7.12`, if it's installed.
+        if let Some(pyenv_root) = pyenv_root() {
+            let exe_path = pyenv_root.join("versions").join(version).join("bin/python");
+            if exe_path.exists() {
+                return Ok(Some(Self::Executable(exe_path)));
+            }
+        }
+
+        // pyenv-style versions can include a suffix, i.e. `pypy3.10-7.3.12`.
+        let request: Self = version
+            .split('-')
+            .next()
+            .unwrap_or(version)
+            .parse()
+            .with_context(|| format!("Invalid version in {path:?}"))?;
+        if let Self::Executable(_) = request {
+            anyhow::bail!("{path:?} names `{version}`, which is not installed");
+        }
+        Ok(Some(request))
+    }
+
+    fn from_pyproject(path: &Path) -> anyhow::Result<Option<Self>> {
+        let document: Document = std::fs::read_to_string(path)?
+            .parse()
+            .with_context(|| format!("Failed to parse {path:?}"))?;
+
+        let requires_python = document
+            .get("project")
+            .and_then(|p| p.get("requires-python"))
+            .and_then(|v| v.as_str());
+        let Some(requires_python) = requires_python else {
+            return Ok(None);
+        };
+        requires_python
+            .parse()
+            .map(Some)
+            .with_context(|| format!("Invalid requires-python in {path:?}"))
+    }
+
+    pub fn matches(&self, python: &Python) -> bool {
+        match self {
+            Self::Executable(_) => true,
+            Self::Version {
+                implementation,
+                specifiers,
+            } => {
+                let implementation = implementation.as_deref().unwrap_or("cpython");
+                let version = python.version_parts();
+
+                python.implementation == implementation
+                    && specifiers.0.iter().all(|s| specifier_matches(s, &version))
+            }
+        }
+    }
+}
+
+impl FromStr for PythonRequest {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.contains('/') {
+            return Ok(Self::Executable(PathBuf::from(value)));
+        }
+
+        let (implementation, rest) = ["cpython", "pypy"]
+            .iter()
+            .find_map(|i| {
+                value
+                    .strip_prefix(i)
+                    .map(|rest| (Some(i.to_string()), rest))
+            })
+            .unwrap_or((None, value));
+
+        if rest.is_empty() {
+            return Ok(Self::Version {
+                implementation,
+                specifiers: Specifiers::default(),
+            });
+        }
+
+        let specifiers = if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            let value = if rest.split('.').count() < 3 {
+                format!("=={rest}.*")
+            } else {
+                format!("=={rest}")
+            };
+            Specifiers::from_str(&value)
+        } else if rest.starts_with(['<', '>', '=', '!', '~']) {
+            Specifiers::from_str(rest)
+        } else {
+            return Ok(Self::Executable(PathBuf::from(value)));
+        };
+
+        Ok(Self::Version {
+            implementation,
+            specifiers: specifiers.map_err(|e| anyhow::anyhow!("{e}"))?,
+        })
+    }
+}
+
+impl Display for PythonRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Executable(path) => write!(f, "{}", path.to_string_lossy()),
+            Self::Version {
+                implementation,
+                specifiers,
+            } => {
+                if let Some(implementation) = implementation {
+                    write!(f, "{implementation} ")?;
+                }
+                write!(f, "{specifiers}")
+            }
+        }
+    }
+}
+
+/// Whether `python` satisfies a (`Requires-Python`) specifier, i.e. `>=3.8`.
+/// Specifiers which can't be parsed are ignored.
+pub fn satisfies(requires_python: &str, python: &Python) -> bool {
+    let Ok(specifiers) = Specifiers::from_str(requires_python.trim()) else {
+        return true;
+    };
+    let version = python.version_parts();
+    specifiers.0.iter().all(|s| specifier_matches(s, &version))
+}
+
+fn parse_version(value: &str) -> Vec<u64> {
+    value
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Compares release segments, treating missing trailing segments as zero.
+fn compare(version: &[u64], other: &[u64]) -> std::cmp::Ordering {
+    let len = version.len().max(other.len());
+    for i in 0..len {
+        let a = version.get(i).copied().unwrap_or(0);
+        let b = other.get(i).copied().unwrap_or(0);
+        if a != b {
+            return a.cmp(&b);
+        }
+    }
+    std::cmp::Ordering::Equal
+}
+
+fn specifier_matches(specifier: &Specifier, version: &[u64]) -> bool {
+    use std::cmp::Ordering::*;
+
+    if let Some(prefix) = specifier.value.strip_suffix(".*") {
+        let prefix = parse_version(prefix);
+        let matches = version.len() >= prefix.len() && version[..prefix.len()] == prefix[..];
+        return match specifier.op {
+            CompareOp::NotEqual => !matches,
+            _ => matches,
+        };
+    }
+
+    let value = parse_version(&specifier.value);
+    let ordering = compare(version, &value);
+    match specifier.op {
+        CompareOp::Equal => ordering == Equal,
+        CompareOp::NotEqual => ordering != Equal,
+        CompareOp::LessThanEqual => ordering != Greater,
+        CompareOp::StrictlyLessThan => ordering == Less,
+        CompareOp::GreaterThanEqual => ordering != Less,
+        CompareOp::StrictlyGreaterThan => ordering == Greater,
+        CompareOp::Compatible => {
+            // `~=3.9` is `>=3.9,==3.*`.
+            let prefix = &value[..value.len().saturating_sub(1)];
+            ordering != Less && version.len() >= prefix.len() && version[..prefix.len()] == *prefix
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn specifier(value: &str) -> Specifier {
+        Specifiers::from_str(value).unwrap().0.remove(0)
+    }
+
+    fn version_request(value: &str) -> (Option<String>, String) {
+        match value.parse().unwrap() {
+            PythonRequest::Version {
+                implementation,
+                specifiers,
+            } => (implementation, specifiers.to_string()),
+            PythonRequest::Executable(path) => panic!("{value} parsed as executable {path:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_executables() {
+        for value in ["/usr/bin/python3", "./python", "python3", "python"] {
+            assert!(
+                matches!(value.parse(), Ok(PythonRequest::Executable(_))),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_versions() {
+        assert_eq!(version_request("3"), (None, "== 3.*".to_string()));
+        assert_eq!(version_request("3.11"), (None, "== 3.11.*".to_string()));
+        assert_eq!(version_request("3.11.4"), (None, "== 3.11.4".to_string()));
+        assert_eq!(
+            version_request("pypy3.10"),
+            (Some("pypy".to_string()), "== 3.10.*".to_string())
+        );
+        assert_eq!(
+            version_request("cpython"),
+            (Some("cpython".to_string()), String::new())
+        );
+        assert_eq!(
+            version_request(">=3.9,<3.12"),
+            (None, ">= 3.9, < 3.12".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_specifiers() {
+        assert!(PythonRequest::from_str(">=").is_err());
+        assert!(PythonRequest::from_str("<<3").is_err());
+    }
+
+    #[test]
+    fn matches_comparisons() {
+        assert!(specifier_matches(&specifier(">=3.9"), &[3, 9, 0]));
+        assert!(specifier_matches(&specifier(">=3.9"), &[3, 12, 1]));
+        assert!(!specifier_matches(&specifier(">=3.9"), &[3, 8, 18]));
+        assert!(specifier_matches(&specifier("<3.12"), &[3, 11, 9]));
+        assert!(!specifier_matches(&specifier("<3.12"), &[3, 12, 0]));
+        assert!(specifier_matches(&specifier("==3.11"), &[3, 11, 0]));
+        assert!(!specifier_matches(&specifier("==3.11"), &[3, 11, 4]));
+        assert!(specifier_matches(&specifier("!=3.11.4"), &[3, 11, 5]));
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(specifier_matches(&specifier("==3.11.*"), &[3, 11, 4]));
+        assert!(!specifier_matches(&specifier("==3.11.*"), &[3, 1, 1]));
+        assert!(!specifier_matches(&specifier("==3.11.*"), &[3, 12, 0]));
+        assert!(specifier_matches(&specifier("!=3.11.*"), &[3, 12, 0]));
+        assert!(!specifier_matches(&specifier("!=3.11.*"), &[3, 11, 0]));
+    }
+
+    #[test]
+    fn matches_compatible_releases() {
+        assert!(specifier_matches(&specifier("~=3.9"), &[3, 12, 0]));
+        assert!(!specifier_matches(&specifier("~=3.9"), &[3, 8, 0]));
+        assert!(!specifier_matches(&specifier("~=3.9"), &[4, 0, 0]));
+        assert!(specifier_matches(&specifier("~=3.9.2"), &[3, 9, 5]));
+        assert!(!specifier_matches(&specifier("~=3.9.2"), &[3, 10, 0]));
+    }
+}
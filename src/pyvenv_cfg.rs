@@ -0,0 +1,211 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// A venv's `pyvenv.cfg`, as written by prp, `python -m venv`, virtualenv, etc.
+///
+/// Lines are retained as-is, so that rewriting the file preserves any keys (or
+/// comments) which prp doesn't itself know about.
+#[derive(Debug, Default)]
+pub struct PyvenvCfg {
+    lines: Vec<Line>,
+}
+
+#[derive(Debug)]
+enum Line {
+    Entry { key: String, value: String },
+    Other(String),
+}
+
+impl PyvenvCfg {
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let lines = content
+            .lines()
+            .map(|line| match line.split_once('=') {
+                Some((key, value)) => Line::Entry {
+                    key: key.trim().to_string(),
+                    value: value.trim().to_string(),
+                },
+                None => Line::Other(line.to_string()),
+            })
+            .collect();
+        Self { lines }
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        Ok(std::fs::write(path, self.to_string())?)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter().find_map(|line| match line {
+            Line::Entry { key: k, value } if k == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) {
+        for line in self.lines.iter_mut() {
+            if let Line::Entry { key: k, value: v } = line {
+                if k == key {
+                    *v = value.to_string();
+                    return;
+                }
+            }
+        }
+
+        self.lines.push(Line::Entry {
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.lines
+            .retain(|line| !matches!(line, Line::Entry { key: k, .. } if k == key));
+    }
+
+    pub fn home(&self) -> Option<PathBuf> {
+        self.get("home").map(PathBuf::from)
+    }
+
+    /// The python version, i.e. `3.11.4`. virtualenv (and uv) record this as
+    /// `version_info`, possibly with a release level suffix (`3.11.4.final.0`).
+    pub fn version(&self) -> Option<String> {
+        if let Some(version) = self.get("version") {
+            return Some(version.to_string());
+        }
+
+        let version_info = self.get("version_info")?;
+        let parts: Vec<&str> = version_info.split('.').take(3).collect();
+        Some(parts.join("."))
+    }
+
+    /// The base interpreter the venv was created from.
+    pub fn executable(&self) -> Option<PathBuf> {
+        if let Some(executable) = self.get("executable").or(self.get("base-executable")) {
+            return Some(PathBuf::from(executable));
+        }
+
+        // Older versions of `python -m venv` only record the interpreter's
+        // directory; which can hold other pythons than the venv's, so the one
+        // named for the recorded version is preferred (i.e. `python3.9` beside
+        // a `python3` which is 3.11).
+        let home = self.home()?;
+        let versioned = self.version().and_then(|version| {
+            let mut parts = version.split('.');
+            Some(format!("python{}.{}", parts.next()?, parts.next()?))
+        });
+        versioned
+            .into_iter()
+            .chain(["python3".to_string(), "python".to_string()])
+            .map(|name| home.join(name))
+            .find(|path| path.exists())
+    }
+
+    pub fn prompt(&self) -> Option<String> {
+        self.get("prompt")
+            .map(|prompt| prompt.trim_matches(|c| c == '\'' || c == '"').to_string())
+    }
+
+    pub fn include_system_site_packages(&self) -> bool {
+        self.get("include-system-site-packages")
+            .map(|value| value.eq_ignore_ascii_case("true"))
+            .unwrap_or(false)
+    }
+}
+
+impl Display for PyvenvCfg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            match line {
+                Line::Entry { key, value } => writeln!(f, "{key} = {value}")?,
+                Line::Other(line) => writeln!(f, "{line}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VENV_CFG: &str = indoc::indoc! {"
+        home = /usr/bin
+        include-system-site-packages = false
+        version = 3.11.4
+        executable = /usr/bin/python3.11
+        # a comment
+        prompt = 'proj'
+    "};
+
+    #[test]
+    fn round_trips_unchanged() {
+        let cfg = PyvenvCfg::parse(VENV_CFG);
+        assert_eq!(cfg.to_string(), VENV_CFG);
+    }
+
+    #[test]
+    fn normalizes_spacing_around_equals() {
+        let cfg = PyvenvCfg::parse("home=/usr/bin\nversion   =  3.11.4\n");
+        assert_eq!(cfg.to_string(), "home = /usr/bin\nversion = 3.11.4\n");
+    }
+
+    #[test]
+    fn reads_values() {
+        let cfg = PyvenvCfg::parse(VENV_CFG);
+        assert_eq!(cfg.home(), Some(PathBuf::from("/usr/bin")));
+        assert_eq!(cfg.version().as_deref(), Some("3.11.4"));
+        assert_eq!(cfg.executable(), Some(PathBuf::from("/usr/bin/python3.11")));
+        assert_eq!(cfg.prompt().as_deref(), Some("proj"));
+        assert!(!cfg.include_system_site_packages());
+        assert_eq!(cfg.get("missing"), None);
+    }
+
+    #[test]
+    fn reads_virtualenv_version_info() {
+        let cfg = PyvenvCfg::parse("version_info = 3.11.4.final.0\n");
+        assert_eq!(cfg.version().as_deref(), Some("3.11.4"));
+    }
+
+    #[test]
+    fn executable_of_home_prefers_the_recorded_version() {
+        let home = std::env::temp_dir().join(format!("prp-home-{}", std::process::id()));
+        std::fs::create_dir_all(&home).unwrap();
+        for name in ["python3", "python3.9"] {
+            std::fs::write(home.join(name), "").unwrap();
+        }
+
+        let cfg = |version: &str| {
+            PyvenvCfg::parse(&format!("home = {}\nversion = {version}\n", home.display()))
+        };
+        assert_eq!(cfg("3.9.18").executable(), Some(home.join("python3.9")));
+        assert_eq!(cfg("3.11.4").executable(), Some(home.join("python3")));
+
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn set_and_remove_preserve_other_lines() {
+        let mut cfg = PyvenvCfg::parse(VENV_CFG);
+        cfg.set("version", "3.11.5");
+        cfg.set("prp-copies", "true");
+        cfg.remove("prompt");
+
+        let cfg = PyvenvCfg::parse(&cfg.to_string());
+        assert_eq!(
+            cfg.to_string(),
+            indoc::indoc! {"
+                home = /usr/bin
+                include-system-site-packages = false
+                version = 3.11.5
+                executable = /usr/bin/python3.11
+                # a comment
+                prp-copies = true
+            "}
+        );
+    }
+}
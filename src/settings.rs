@@ -1,47 +1,229 @@
 use etcetera::base_strategy::{BaseStrategy, Xdg};
-use std::fs::File;
-use std::io::{BufReader, Read};
+use std::collections::BTreeMap;
+use std::fmt::Display;
 use std::path::{Path, PathBuf};
-use toml_edit::Document;
+use std::str::FromStr;
+use toml_edit::{Document, Item, Table};
+
+use crate::python::{installation_bin_dirs, PythonRequest};
+
+/// The type of a setting's value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    String,
+    Bool,
+    Integer,
+    /// An array of strings; given in environment variables as a path list.
+    List,
+    /// A string, which must be one of the given values.
+    Choice(&'static [&'static str]),
+}
+
+pub struct Key {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub doc: &'static str,
+}
+
+impl Key {
+    /// The environment variable which overrides this setting, i.e. `PRP_VENV_NAME`.
+    pub fn env_var(&self, name: &str) -> String {
+        format!("{}_{}", name, self.name)
+            .to_uppercase()
+            .replace(['-', '.'], "_")
+    }
+}
+
+/// Every recognized setting.
+pub const KEYS: &[Key] = &[
+    Key {
+        name: "config-mode",
+        kind: Kind::Choice(&["strict", "default", "lenient"]),
+        doc: "How strictly the config is validated",
+    },
+    Key {
+        name: "venv-name",
+        kind: Kind::String,
+        doc: "Name of the venv directory",
+    },
+    Key {
+        name: "strategy",
+        kind: Kind::Choice(&["local", "central", "named", "git", "template"]),
+        doc: "Where venvs are placed",
+    },
+    Key {
+        name: "named-venvs-path",
+        kind: Kind::String,
+        doc: "Where the `named` strategy keeps venvs",
+    },
+    Key {
+        name: "venv-path",
+        kind: Kind::String,
+        doc: "The venv path template for the `template` strategy",
+    },
+    Key {
+        name: "project-root",
+        kind: Kind::List,
+        doc: "Files which mark a project's directory",
+    },
+    Key {
+        name: "auto-activate",
+        kind: Kind::Bool,
+        doc: "Whether `prp venv` activates the venv",
+    },
+    Key {
+        name: "python",
+        kind: Kind::String,
+        doc: "The python to create venvs with: a path, executable or version",
+    },
+    Key {
+        name: "python-search-path",
+        kind: Kind::List,
+        doc: "Extra directories to search for interpreters",
+    },
+    Key {
+        name: "pythons-path",
+        kind: Kind::String,
+        doc: "Where `prp python install` installs interpreters",
+    },
+    Key {
+        name: "python-mirror",
+        kind: Kind::String,
+        doc: "URL template for `prp python install` archives",
+    },
+    Key {
+        name: "executables-path",
+        kind: Kind::String,
+        doc: "Where `prp x` keeps each executable's venv",
+    },
+    Key {
+        name: "wheel-cache-path",
+        kind: Kind::String,
+        doc: "Where unpacked wheels are cached",
+    },
+    Key {
+        name: "link-mode",
+        kind: Kind::Choice(&["copy", "hardlink", "reflink"]),
+        doc: "How wheel files are installed",
+    },
+    Key {
+        name: "pip-wheel",
+        kind: Kind::String,
+        doc: "A pip wheel to seed new venvs with",
+    },
+    Key {
+        name: "seed-packages",
+        kind: Kind::List,
+        doc: "Requirements installed into every new venv",
+    },
+    Key {
+        name: "requirements",
+        kind: Kind::String,
+        doc: "A requirements file (relative to the project) installed into its new venvs",
+    },
+    Key {
+        name: "hooks.post-create",
+        kind: Kind::String,
+        doc: "Command run after a venv is created",
+    },
+    Key {
+        name: "hooks.post-install",
+        kind: Kind::String,
+        doc: "Command run after prp installs packages into a venv",
+    },
+    Key {
+        name: "hooks.pre-activate",
+        kind: Kind::String,
+        doc: "Command run before a venv is activated",
+    },
+    Key {
+        name: "hooks.post-activate",
+        kind: Kind::String,
+        doc: "Command run after a venv is activated",
+    },
+    Key {
+        name: "lock-timeout",
+        kind: Kind::Integer,
+        doc: "Seconds to wait for another prp process using the same venv",
+    },
+];
 
 pub struct Settings {
     pub name: String,
     pub config_file: PathBuf,
+    pub data_dir: PathBuf,
+    pub projects_file: PathBuf,
+    pub project_dir: PathBuf,
+    pub origins: BTreeMap<String, Origin>,
+    pub config_mode: ConfigMode,
 
     pub venv_name: String,
     pub strategy: Strategy,
+    pub named_venvs_path: PathBuf,
+    pub venv_path: Option<String>,
     pub project_root: Vec<String>,
     pub auto_activate: bool,
-    pub python_path: PathBuf,
+    pub python: PythonRequest,
+    pub python_search_path: Vec<PathBuf>,
+    pub pythons_path: PathBuf,
+    pub python_mirror: Option<String>,
 
     pub executables_path: PathBuf,
+    pub wheel_cache_path: PathBuf,
+    pub link_mode: LinkMode,
+    pub pip_wheel: Option<PathBuf>,
+    pub seed_packages: Vec<String>,
+    pub requirements: Option<PathBuf>,
+    pub hooks: Hooks,
+    pub lock_dir: PathBuf,
+    pub lock_timeout: u64,
 }
 
 impl Settings {
-    pub fn read(name: &str) -> anyhow::Result<Self> {
+    /// Reads settings from each source, where later sources take precedence:
+    /// built-in defaults, the global config file, the project's
+    /// `pyproject.toml` `[tool.prp]` table, the project's `.prp.toml`, the
+    /// project's entry in the data directory's `projects.toml` (i.e. from
+    /// `prp venv use`), and then `PRP_*` environment variables.
+    ///
+    /// Problems with any of them (unparseable files, unknown keys, and values
+    /// of the wrong type) are errors or warnings, depending on `config-mode`.
+    ///
+    /// `mode` overrides the configured `config-mode`; i.e. so that a broken
+    /// config can still be fixed with `prp config`.
+    pub fn read(name: &str, mode: Option<ConfigMode>) -> anyhow::Result<Self> {
         let strategy = Xdg::new()?;
         let config_dir = strategy.config_dir().join(name);
         let data_dir = strategy.data_dir().join(name);
+        let cache_dir = strategy.cache_dir().join(name);
 
         let config_file = config_dir.with_extension("toml");
 
-        let content = read_file(&config_file);
-        let document = &get_document(content);
-
-        let venv_name = document
-            .get("venv-name")
-            .and_then(|v| v.as_str())
-            .unwrap_or(".venv")
-            .to_string();
+        let mut problems = vec![];
+        let (document, source) = read_document(&config_file, &mut problems);
+        let global = Layer {
+            origin: Origin::Global(config_file.clone()),
+            table: document.as_table().clone(),
+            source,
+            header: vec![],
+        };
+        let env_layers = env_layers(name);
 
-        let strategy = Strategy::from(document.get("strategy").and_then(|v| v.as_str()));
-        let project_root = document
+        // The project can't decide what identifies the project, so only the
+        // global settings (and the environment) apply to `project-root`.
+        let mut root_layers = Layers::default();
+        root_layers.push(global.clone());
+        for layer in &env_layers {
+            root_layers.push(layer.clone());
+        }
+        let project_root: Vec<String> = root_layers
             .get("project-root")
-            .and_then(|t| t.as_array())
-            .map(|t| {
-                t.iter()
-                    .map(|v| v.as_str().unwrap_or("").to_string())
-                    .collect()
+            .and_then(|t| {
+                t.as_array().map(|t| {
+                    t.iter()
+                        .map(|v| v.as_str().unwrap_or("").to_string())
+                        .collect()
+                })
             })
             .unwrap_or_else(|| {
                 ["pyproject.toml", "setup.py", "setup.cfg", ".gitignore"]
@@ -50,53 +232,784 @@ impl Settings {
                     .collect()
             });
 
-        let auto_activate = document
+        // Keeps `project-root`'s origin.
+        let mut layers = Layers {
+            layers: vec![global],
+            origins: root_layers.origins,
+        };
+
+        let project_dir = find_project_root(&std::env::current_dir()?, &project_root);
+
+        let pyproject = project_dir.join("pyproject.toml");
+        let mut pyproject_problems = vec![];
+        let (document, source) = read_document(&pyproject, &mut pyproject_problems);
+        // The file belongs to the project (and its other tools) rather than to
+        // prp, so failing to parse it shouldn't stop every prp command.
+        problems.extend(pyproject_problems.into_iter().map(|problem| Problem {
+            kind: ProblemKind::Foreign,
+            ..problem
+        }));
+        if let Some(table) = document
+            .get("tool")
+            .and_then(|t| t.get(name))
+            .and_then(|t| t.as_table())
+        {
+            layers.push(Layer {
+                origin: Origin::Pyproject(pyproject),
+                table: table.clone(),
+                source,
+                header: vec!["tool".to_string(), name.to_string()],
+            });
+        }
+
+        let project_file = project_dir.join(format!(".{name}.toml"));
+        let (document, source) = read_document(&project_file, &mut problems);
+        layers.push(Layer {
+            origin: Origin::ProjectFile(project_file),
+            table: document.as_table().clone(),
+            source,
+            header: vec![],
+        });
+
+        // The project's settings chosen with commands like `prp venv use`, kept
+        // outside of the project.
+        let projects_file = data_dir.join("projects.toml");
+        let (document, source) = read_document(&projects_file, &mut problems);
+        let project_key = project_dir.to_string_lossy().to_string();
+        if let Some(table) = document.get(&project_key).and_then(|t| t.as_table()) {
+            layers.push(Layer {
+                origin: Origin::Projects(projects_file.clone()),
+                table: table.clone(),
+                source,
+                header: vec![project_key],
+            });
+        }
+        for layer in env_layers {
+            layers.push(layer);
+        }
+
+        let configured_mode =
+            ConfigMode::from(layers.get("config-mode").as_ref().and_then(|v| v.as_str()));
+        layers.validate(&mut problems);
+        report(mode.unwrap_or(configured_mode), problems)?;
+
+        let venv_name = layers
+            .get("venv-name")
+            .and_then(|v| v.as_str().map(String::from))
+            .unwrap_or(".venv".to_string());
+
+        let strategy = Strategy::from(layers.get("strategy").as_ref().and_then(|v| v.as_str()));
+
+        let named_venvs_path = layers
+            .get("named-venvs-path")
+            .and_then(|v| v.as_str().map(PathBuf::from))
+            .unwrap_or(data_dir.join("named"));
+
+        let venv_path = layers
+            .get("venv-path")
+            .and_then(|v| v.as_str().map(String::from));
+
+        let auto_activate = layers
             .get("auto-activate")
             .and_then(|v| v.as_bool())
             .unwrap_or(true);
 
-        let python_path = Path::new(
-            document
-                .get("python-path")
-                .and_then(|v| v.as_str())
-                .unwrap_or("python"),
-        )
-        .to_path_buf();
+        let python = layers
+            .get("python")
+            .or_else(|| {
+                let python_path = layers.get("python-path")?;
+                let origin = layers.origin("python-path");
+                layers.origins.insert("python".to_string(), origin);
+                Some(python_path)
+            })
+            .and_then(|v| v.as_str().and_then(|v| v.parse().ok()))
+            .unwrap_or_else(|| PythonRequest::Executable(PathBuf::from("python")));
+
+        let python_search_path = layers
+            .get("python-search-path")
+            .and_then(|t| {
+                t.as_array().map(|t| {
+                    t.iter()
+                        .filter_map(|v| v.as_str())
+                        .map(PathBuf::from)
+                        .collect()
+                })
+            })
+            .unwrap_or_default();
 
-        let executables_path = document
+        let pythons_path = layers
+            .get("pythons-path")
+            .and_then(|v| v.as_str().map(PathBuf::from))
+            .unwrap_or(data_dir.join("pythons"));
+
+        let python_mirror = layers
+            .get("python-mirror")
+            .and_then(|v| v.as_str().map(String::from));
+
+        let executables_path = layers
             .get("executables-path")
-            .and_then(|v| v.as_str())
-            .map(|v| Path::new(v).to_path_buf())
+            .and_then(|v| v.as_str().map(PathBuf::from))
             .unwrap_or(data_dir.join("venvs"));
 
+        let wheel_cache_path = layers
+            .get("wheel-cache-path")
+            .and_then(|v| v.as_str().map(PathBuf::from))
+            .unwrap_or(cache_dir.join("wheels"));
+
+        let link_mode = LinkMode::from(layers.get("link-mode").as_ref().and_then(|v| v.as_str()));
+
+        let pip_wheel = layers
+            .get("pip-wheel")
+            .and_then(|v| v.as_str().map(PathBuf::from));
+
+        let seed_packages = layers
+            .get("seed-packages")
+            .and_then(|t| {
+                t.as_array().map(|t| {
+                    t.iter()
+                        .filter_map(|v| v.as_str())
+                        .map(String::from)
+                        .collect()
+                })
+            })
+            .unwrap_or_default();
+
+        let requirements = layers
+            .get("requirements")
+            .and_then(|v| v.as_str().map(PathBuf::from));
+
+        let mut hooks = Hooks::default();
+        for hook in Hook::ALL {
+            let command = layers
+                .get(hook.key())
+                .and_then(|v| v.as_str().map(String::from));
+            hooks.set(hook, command);
+        }
+
+        let lock_timeout = layers
+            .get("lock-timeout")
+            .and_then(|v| v.as_integer())
+            .and_then(|v| u64::try_from(v).ok())
+            .unwrap_or(300);
+
         Ok(Self {
             name: name.to_string(),
             config_file,
+            data_dir,
+            projects_file,
+            project_dir,
+            origins: layers.origins,
+            config_mode: configured_mode,
             venv_name,
             strategy,
+            named_venvs_path,
+            venv_path,
             project_root,
             auto_activate,
-            python_path,
+            python,
+            python_search_path,
+            pythons_path,
+            python_mirror,
             executables_path,
+            wheel_cache_path,
+            link_mode,
+            pip_wheel,
+            seed_packages,
+            requirements,
+            hooks,
+            lock_dir: cache_dir.join("locks"),
+            lock_timeout,
         })
     }
 
-    pub fn set_python_path(&mut self, maybe_path: Option<PathBuf>) {
-        if let Some(path) = maybe_path {
-            self.python_path = path;
+    /// The effective value of a setting, if it has one.
+    pub fn value(&self, key: &str) -> Option<Item> {
+        let path = |path: &Path| toml_edit::value(path.to_string_lossy().to_string());
+        let list = |values: &mut dyn Iterator<Item = String>| {
+            toml_edit::value(values.collect::<toml_edit::Array>())
+        };
+
+        let item = match key {
+            "config-mode" => toml_edit::value(self.config_mode.to_string()),
+            "venv-name" => toml_edit::value(&self.venv_name),
+            "strategy" => toml_edit::value(self.strategy.to_string()),
+            "named-venvs-path" => path(&self.named_venvs_path),
+            "venv-path" => toml_edit::value(self.venv_path.as_ref()?),
+            "project-root" => list(&mut self.project_root.iter().cloned()),
+            "auto-activate" => toml_edit::value(self.auto_activate),
+            "python" => toml_edit::value(self.python.to_string()),
+            "python-search-path" => list(
+                &mut self
+                    .python_search_path
+                    .iter()
+                    .map(|p| p.to_string_lossy().to_string()),
+            ),
+            "pythons-path" => path(&self.pythons_path),
+            "python-mirror" => toml_edit::value(self.python_mirror.as_ref()?),
+            "executables-path" => path(&self.executables_path),
+            "wheel-cache-path" => path(&self.wheel_cache_path),
+            "link-mode" => toml_edit::value(self.link_mode.to_string()),
+            "pip-wheel" => path(self.pip_wheel.as_ref()?),
+            "seed-packages" => list(&mut self.seed_packages.iter().cloned()),
+            "requirements" => path(self.requirements.as_ref()?),
+            "hooks.post-create" => toml_edit::value(self.hooks.get(Hook::PostCreate)?),
+            "hooks.post-install" => toml_edit::value(self.hooks.get(Hook::PostInstall)?),
+            "hooks.pre-activate" => toml_edit::value(self.hooks.get(Hook::PreActivate)?),
+            "hooks.post-activate" => toml_edit::value(self.hooks.get(Hook::PostActivate)?),
+            "lock-timeout" => toml_edit::value(self.lock_timeout as i64),
+            _ => return None,
+        };
+        Some(item)
+    }
+
+    /// Where the effective value of a setting came from.
+    pub fn origin(&self, key: &str) -> Origin {
+        self.origins.get(key).cloned().unwrap_or(Origin::Default)
+    }
+
+    /// Where to look for interpreters, beyond the standard locations:
+    /// configured directories, followed by prp-installed pythons.
+    pub fn python_search_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = self.python_search_path.clone();
+        dirs.extend(installation_bin_dirs(&self.pythons_path));
+        dirs
+    }
+
+    pub fn set_python(&mut self, maybe_python: Option<PythonRequest>) {
+        if let Some(python) = maybe_python {
+            self.python = python;
+            self.origins.insert("python".to_string(), Origin::Cli);
         }
     }
 
     pub fn set_venv_name(&mut self, maybe_name: Option<String>) {
         if let Some(name) = maybe_name {
             self.venv_name = name;
+            self.origins.insert("venv-name".to_string(), Origin::Cli);
+        }
+    }
+}
+
+/// A point at which a configured command is run in the venv.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hook {
+    PostCreate,
+    PostInstall,
+    PreActivate,
+    PostActivate,
+}
+
+impl Hook {
+    pub const ALL: [Hook; 4] = [
+        Self::PostCreate,
+        Self::PostInstall,
+        Self::PreActivate,
+        Self::PostActivate,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::PostCreate => "hooks.post-create",
+            Self::PostInstall => "hooks.post-install",
+            Self::PreActivate => "hooks.pre-activate",
+            Self::PostActivate => "hooks.post-activate",
+        }
+    }
+}
+
+/// The configured command for each hook, if any.
+#[derive(Clone, Debug, Default)]
+pub struct Hooks {
+    post_create: Option<String>,
+    post_install: Option<String>,
+    pre_activate: Option<String>,
+    post_activate: Option<String>,
+}
+
+impl Hooks {
+    pub fn get(&self, hook: Hook) -> Option<&String> {
+        match hook {
+            Hook::PostCreate => self.post_create.as_ref(),
+            Hook::PostInstall => self.post_install.as_ref(),
+            Hook::PreActivate => self.pre_activate.as_ref(),
+            Hook::PostActivate => self.post_activate.as_ref(),
+        }
+    }
+
+    fn set(&mut self, hook: Hook, command: Option<String>) {
+        let field = match hook {
+            Hook::PostCreate => &mut self.post_create,
+            Hook::PostInstall => &mut self.post_install,
+            Hook::PreActivate => &mut self.pre_activate,
+            Hook::PostActivate => &mut self.post_activate,
+        };
+        *field = command;
+    }
+}
+
+/// Where a setting's value came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Origin {
+    Default,
+    Global(PathBuf),
+    Pyproject(PathBuf),
+    ProjectFile(PathBuf),
+    Projects(PathBuf),
+    Env(String),
+    Cli,
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Global(path) => write!(f, "{}", path.to_string_lossy()),
+            Self::Pyproject(path) => write!(f, "{} [tool.prp]", path.to_string_lossy()),
+            Self::ProjectFile(path) => write!(f, "{}", path.to_string_lossy()),
+            Self::Projects(path) => write!(f, "{} (prp venv use)", path.to_string_lossy()),
+            Self::Env(var) => write!(f, "${var}"),
+            Self::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+/// A source of settings.
+#[derive(Clone)]
+struct Layer {
+    origin: Origin,
+    table: Table,
+
+    /// The text the table was parsed from, to locate problems within it.
+    source: String,
+
+    /// The table header the settings are nested under in `source`, if any.
+    header: Vec<String>,
+}
+
+impl Layer {
+    /// Where `key` (within the nested `table`) is defined, i.e. `prp.toml:3`.
+    fn location(&self, table: &[&str], key: &str) -> String {
+        match &self.origin {
+            Origin::Global(path)
+            | Origin::Pyproject(path)
+            | Origin::ProjectFile(path)
+            | Origin::Projects(path) => {
+                let mut header: Vec<&str> = self.header.iter().map(String::as_str).collect();
+                header.extend(table);
+
+                match find_line(&self.source, &header, key) {
+                    Some(line) => format!("{}:{line}", path.to_string_lossy()),
+                    None => path.to_string_lossy().to_string(),
+                }
+            }
+            origin => origin.to_string(),
+        }
+    }
+}
+
+/// The sources of settings, in increasing order of precedence.
+#[derive(Default)]
+struct Layers {
+    layers: Vec<Layer>,
+    origins: BTreeMap<String, Origin>,
+}
+
+impl Layers {
+    fn push(&mut self, layer: Layer) {
+        self.layers.push(layer);
+    }
+
+    /// The highest precedence value of `key`, which may be a dotted path into
+    /// nested tables (`a.b` being `b` within `[a]`). Records where it came from.
+    /// Invalid values are skipped (having been reported by `validate`), so that
+    /// the next layer's value applies instead.
+    fn get(&mut self, key: &str) -> Option<Item> {
+        for layer in self.layers.iter().rev() {
+            let Some(item) = lookup(&layer.table, key) else {
+                continue;
+            };
+            if check_value(key, item).is_ok() {
+                self.origins.insert(key.to_string(), layer.origin.clone());
+                return Some(item.clone());
+            }
+        }
+        None
+    }
+
+    /// Checks every layer for unknown keys and values of the wrong type.
+    fn validate(&self, problems: &mut Vec<Problem>) {
+        for layer in &self.layers {
+            validate_table(layer, &layer.table, &mut vec![], problems);
+        }
+    }
+
+    fn origin(&self, key: &str) -> Origin {
+        self.origins.get(key).cloned().unwrap_or(Origin::Default)
+    }
+}
+
+fn lookup<'a>(table: &'a Table, key: &str) -> Option<&'a Item> {
+    let mut parts = key.split('.');
+    let mut item = table.get(parts.next()?)?;
+    for part in parts {
+        item = item.get(part)?;
+    }
+    Some(item)
+}
+
+fn validate_table<'a>(
+    layer: &Layer,
+    table: &'a Table,
+    path: &mut Vec<&'a str>,
+    problems: &mut Vec<Problem>,
+) {
+    for (key, item) in table.iter() {
+        let name = path
+            .iter()
+            .chain([&key])
+            .copied()
+            .collect::<Vec<_>>()
+            .join(".");
+        let location = layer.location(path, key);
+
+        // Tables which only group other settings, i.e. `[hooks]`.
+        let prefix = format!("{name}.");
+        if item.is_table_like() && KEYS.iter().any(|k| k.name.starts_with(&prefix)) {
+            if let Some(table) = item.as_table() {
+                path.push(key);
+                validate_table(layer, table, path, problems);
+                path.pop();
+                continue;
+            }
+        }
+
+        // `python-path` is a deprecated alias for `python`.
+        if name != "python-path" {
+            if let Err(e) = find_key(&name) {
+                problems.push(Problem {
+                    kind: ProblemKind::Unknown,
+                    message: format!("{location}: {e}"),
+                });
+                continue;
+            }
+        }
+
+        let applies = matches!(layer.origin, Origin::Global(_) | Origin::Env(_));
+        if name == "project-root" && !applies {
+            problems.push(Problem {
+                kind: ProblemKind::Ignored,
+                message: format!(
+                    "{location}: `project-root` only applies in the global config (or environment)"
+                ),
+            });
+        }
+
+        if let Err(expected) = check_value(&name, item) {
+            problems.push(Problem {
+                kind: ProblemKind::Type,
+                message: format!(
+                    "{location}: `{name}` must be {expected}, not {}",
+                    item.to_string().trim()
+                ),
+            });
+        }
+    }
+}
+
+/// Checks that `item` is a valid value for the setting `name`, or else
+/// describes what it must be.
+pub fn check_value(name: &str, item: &Item) -> Result<(), String> {
+    let name = if name == "python-path" {
+        "python"
+    } else {
+        name
+    };
+    let Ok(setting) = find_key(name) else {
+        return Ok(());
+    };
+
+    let expected = match setting.kind {
+        Kind::String if name == "python" => match item.as_str().map(PythonRequest::from_str) {
+            Some(Ok(_)) => return Ok(()),
+            Some(Err(e)) => format!("a path, executable or version ({e})"),
+            None => "a string".to_string(),
+        },
+        Kind::String if item.is_str() => return Ok(()),
+        Kind::Bool if item.is_bool() => return Ok(()),
+        Kind::Integer if item.as_integer().map(|v| v >= 0).unwrap_or(false) => return Ok(()),
+        Kind::List
+            if item
+                .as_array()
+                .map(|a| a.iter().all(|v| v.is_str()))
+                .unwrap_or(false) =>
+        {
+            return Ok(())
+        }
+        Kind::Choice(values) => match item.as_str() {
+            Some(value) if values.contains(&value) => return Ok(()),
+            _ => {
+                let values: Vec<String> = values.iter().map(|v| format!("`{v}`")).collect();
+                format!("one of {}", values.join(", "))
+            }
+        },
+        Kind::String => "a string".to_string(),
+        Kind::Bool => "a boolean".to_string(),
+        Kind::Integer => "a positive integer".to_string(),
+        Kind::List => "an array of strings".to_string(),
+    };
+    Err(expected)
+}
+
+/// The setting named `name`, or an error suggesting the closest match.
+pub fn find_key(name: &str) -> anyhow::Result<&'static Key> {
+    if let Some(key) = KEYS.iter().find(|k| k.name == name) {
+        return Ok(key);
+    }
+
+    let suggestion = KEYS
+        .iter()
+        .map(|k| (strsim::jaro_winkler(name, k.name), k.name))
+        .filter(|(confidence, _)| *confidence > 0.8)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, k)| format!(", did you mean `{k}`?"))
+        .unwrap_or_default();
+    anyhow::bail!("Unknown setting `{name}`{suggestion}")
+}
+
+/// The 1-based line on which `key` is assigned within the table at `table`
+/// (the parts of its header); whether under that header, as a dotted key (i.e.
+/// `hooks.post-create = ...`), or within an inline table.
+fn find_line(source: &str, table: &[&str], key: &str) -> Option<usize> {
+    let target: Vec<&str> = table.iter().copied().chain([key]).collect();
+    let mut current: Vec<String> = vec![];
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            current = split_key(header)
+                .filter(|(_, rest)| rest.starts_with(']'))
+                .map(|(parts, _)| parts)
+                .unwrap_or_default();
+            continue;
+        }
+
+        let Some((parts, rest)) = split_key(line) else {
+            continue;
+        };
+        let Some(value) = rest.strip_prefix('=') else {
+            continue;
+        };
+        let assigned: Vec<&str> = current
+            .iter()
+            .chain(parts.iter())
+            .map(String::as_str)
+            .collect();
+        let is_inline_table = target.starts_with(&assigned) && value.trim_start().starts_with('{');
+        if assigned == target || is_inline_table {
+            return Some(index + 1);
+        }
+    }
+    None
+}
+
+/// Splits the TOML (dotted) key at the start of `text` into its parts, i.e.
+/// `a."b.c"` into `a` and `b.c`; returning them, and whatever follows the key.
+fn split_key(text: &str) -> Option<(Vec<String>, &str)> {
+    let mut parts = vec![];
+    let mut rest = text.trim_start();
+    loop {
+        let (part, after) = if let Some(quoted) = rest.strip_prefix('"') {
+            // Only basic strings have escapes.
+            let mut part = String::new();
+            let mut end = None;
+            let mut chars = quoted.char_indices();
+            while let Some((index, c)) = chars.next() {
+                match c {
+                    '\\' => part.extend(chars.next().map(|(_, c)| c)),
+                    '"' => {
+                        end = Some(index);
+                        break;
+                    }
+                    c => part.push(c),
+                }
+            }
+            (part, &quoted[end? + 1..])
+        } else if let Some(quoted) = rest.strip_prefix('\'') {
+            let end = quoted.find('\'')?;
+            (quoted[..end].to_string(), &quoted[end + 1..])
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+                .unwrap_or(rest.len());
+            if end == 0 {
+                return None;
+            }
+            (rest[..end].to_string(), &rest[end..])
+        };
+
+        parts.push(part);
+        rest = after.trim_start();
+        match rest.strip_prefix('.') {
+            Some(after) => rest = after.trim_start(),
+            None => return Some((parts, rest)),
+        }
+    }
+}
+
+/// How strictly the config is validated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigMode {
+    /// Every problem is an error.
+    Strict,
+    /// Unknown keys are warnings (i.e. settings from a newer prp), as are
+    /// settings which don't apply where they're set and an unparseable
+    /// `pyproject.toml`, while prp's own unparseable files and invalid values
+    /// are errors.
+    Default,
+    /// Every problem is a warning, and invalid values are ignored.
+    Lenient,
+}
+
+impl Display for ConfigMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Strict => write!(f, "strict"),
+            Self::Default => write!(f, "default"),
+            Self::Lenient => write!(f, "lenient"),
+        }
+    }
+}
+
+impl From<Option<&str>> for ConfigMode {
+    fn from(value: Option<&str>) -> Self {
+        match value {
+            Some("strict") => Self::Strict,
+            Some("lenient") => Self::Lenient,
+            _ => Self::Default,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ProblemKind {
+    Parse,
+    /// A file which isn't prp's own (`pyproject.toml`) couldn't be parsed.
+    Foreign,
+    Unknown,
+    /// A setting which has no effect where it's set.
+    Ignored,
+    Type,
+}
+
+struct Problem {
+    kind: ProblemKind,
+    message: String,
+}
+
+/// Prints the problems which are only warnings under `mode`, and fails with
+/// the rest.
+fn report(mode: ConfigMode, problems: Vec<Problem>) -> anyhow::Result<()> {
+    let mut errors = vec![];
+    for problem in problems {
+        let is_error = match mode {
+            ConfigMode::Strict => true,
+            ConfigMode::Default => matches!(problem.kind, ProblemKind::Parse | ProblemKind::Type),
+            ConfigMode::Lenient => false,
+        };
+
+        if is_error {
+            errors.push(problem.message);
+        } else {
+            eprintln!("Warning: {}", problem.message);
+        }
+    }
+
+    if !errors.is_empty() {
+        anyhow::bail!("Invalid config:\n{}", errors.join("\n"));
+    }
+    Ok(())
+}
+
+/// A layer for each setting given by an environment variable, parsed as the
+/// same type as its TOML counterpart.
+fn env_layers(name: &str) -> Vec<Layer> {
+    let mut layers = vec![];
+    for key in KEYS {
+        let var = key.env_var(name);
+        let Some(value) = std::env::var_os(&var) else {
+            continue;
+        };
+
+        let item = env_item(&key.kind, value.to_string_lossy().to_string());
+
+        // Dotted keys are nested, as they would be in a file.
+        let mut table = Table::new();
+        match key.name.split_once('.') {
+            Some((parent, name)) => {
+                let mut inner = Table::new();
+                inner.insert(name, item);
+                table.insert(parent, Item::Table(inner));
+            }
+            None => {
+                table.insert(key.name, item);
+            }
+        }
+        layers.push(Layer {
+            origin: Origin::Env(var),
+            table,
+            source: String::new(),
+            header: vec![],
+        });
+    }
+    layers
+}
+
+/// An environment variable's value, as the TOML value of a setting of `kind`.
+fn env_item(kind: &Kind, value: String) -> Item {
+    match kind {
+        Kind::String | Kind::Choice(_) => toml_edit::value(value),
+        // Anything else is left as a string, to be reported by validation.
+        Kind::Bool => match value.to_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => toml_edit::value(true),
+            "0" | "false" | "no" | "off" | "" => toml_edit::value(false),
+            _ => toml_edit::value(value),
+        },
+        Kind::Integer => match value.parse::<i64>() {
+            Ok(integer) => toml_edit::value(integer),
+            Err(_) => toml_edit::value(value),
+        },
+        Kind::List => {
+            let array: toml_edit::Array = std::env::split_paths(&value)
+                .map(|p| p.to_string_lossy().to_string())
+                .filter(|p| !p.is_empty())
+                .collect();
+            toml_edit::value(array)
         }
     }
 }
 
 pub enum Strategy {
+    /// In the project directory.
     Local,
+    /// Under the data directory, mirroring the project's path.
     Central,
+    /// Under `named-venvs-path`, by venv name alone; i.e. shared across projects.
+    Named,
+    /// As `Central`, but for the git repository, per checked out branch.
+    Git,
+    /// At the path given by the `venv-path` template.
+    Template,
+}
+
+impl Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Local => write!(f, "local"),
+            Self::Central => write!(f, "central"),
+            Self::Named => write!(f, "named"),
+            Self::Git => write!(f, "git"),
+            Self::Template => write!(f, "template"),
+        }
+    }
 }
 
 impl From<Option<&str>> for Strategy {
@@ -104,23 +1017,284 @@ impl From<Option<&str>> for Strategy {
         match value {
             Some("local") => Self::Local,
             Some("central") => Self::Central,
+            Some("named") => Self::Named,
+            Some("git") => Self::Git,
+            Some("template") => Self::Template,
             _ => Self::Local,
         }
     }
 }
 
-fn read_file(path: &Path) -> String {
-    if let Ok(file) = File::open(path) {
-        let mut reader = BufReader::new(file);
+/// How files are placed into a venv from the unpacked wheel cache.
+#[derive(Clone, Copy, Debug)]
+pub enum LinkMode {
+    Copy,
+    Hardlink,
+    Reflink,
+}
 
-        let mut contents = String::new();
-        reader.read_to_string(&mut contents).unwrap_or(0);
-        contents
-    } else {
-        String::new()
+impl Display for LinkMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Copy => write!(f, "copy"),
+            Self::Hardlink => write!(f, "hardlink"),
+            Self::Reflink => write!(f, "reflink"),
+        }
+    }
+}
+
+impl From<Option<&str>> for LinkMode {
+    fn from(value: Option<&str>) -> Self {
+        match value {
+            Some("copy") => Self::Copy,
+            Some("hardlink") => Self::Hardlink,
+            Some("reflink") => Self::Reflink,
+            _ => Self::Copy,
+        }
+    }
+}
+
+pub fn find_project_root(path: &Path, project_root: &[String]) -> PathBuf {
+    for p in path.ancestors() {
+        for root in project_root {
+            if p.join(root).exists() {
+                return p.to_path_buf();
+            }
+        }
+    }
+    path.to_path_buf()
+}
+
+/// Parses a config file, which is empty if missing. Problems are recorded
+/// rather than returned, since whether they're fatal depends on `config-mode`.
+fn read_document(path: &Path, problems: &mut Vec<Problem>) -> (Document, String) {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return (Document::new(), String::new())
+        }
+        Err(e) => {
+            problems.push(Problem {
+                kind: ProblemKind::Parse,
+                message: format!("Failed to read {}: {e}", path.to_string_lossy()),
+            });
+            return (Document::new(), String::new());
+        }
+    };
+
+    match source.parse::<Document>() {
+        Ok(document) => (document, source),
+        Err(e) => {
+            problems.push(Problem {
+                kind: ProblemKind::Parse,
+                message: format!("Failed to parse {}: {e}", path.to_string_lossy()),
+            });
+            (Document::new(), source)
+        }
     }
 }
 
-fn get_document(contents: String) -> Document {
-    contents.parse::<Document>().unwrap_or(Document::new())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(toml: &str) -> Item {
+        let document: Document = format!("value = {toml}").parse().unwrap();
+        document["value"].clone()
+    }
+
+    #[test]
+    fn find_line_in_tables() {
+        let source = indoc::indoc! {r#"
+            venv-name = ".venv"
+
+            [hooks]
+            post-create = "a"
+
+            [tool.prp]
+            python = "3.11"
+        "#};
+        assert_eq!(find_line(source, &[], "venv-name"), Some(1));
+        assert_eq!(find_line(source, &["hooks"], "post-create"), Some(4));
+        assert_eq!(find_line(source, &["tool", "prp"], "python"), Some(7));
+        assert_eq!(find_line(source, &[], "python"), None);
+        assert_eq!(find_line(source, &["hooks"], "post-install"), None);
+    }
+
+    #[test]
+    fn find_line_under_quoted_headers() {
+        // As `projects.toml` is written, keyed by project paths.
+        let source = indoc::indoc! {r#"
+            ["/home/u/other.proj"]
+            venv-name = "a"
+
+            ["/home/u/proj"]
+            venv-name = "b"
+            'strategy' = "named"
+        "#};
+        assert_eq!(find_line(source, &["/home/u/proj"], "venv-name"), Some(5));
+        assert_eq!(
+            find_line(source, &["/home/u/other.proj"], "venv-name"),
+            Some(2)
+        );
+        assert_eq!(find_line(source, &["/home/u/proj"], "strategy"), Some(6));
+    }
+
+    #[test]
+    fn find_line_of_dotted_keys_and_inline_tables() {
+        let source = indoc::indoc! {r#"
+            hooks.post-create = "a"
+            hooks = { post-install = "b" }
+
+            [tool]
+            prp.python = "3.11"
+        "#};
+        assert_eq!(find_line(source, &["hooks"], "post-create"), Some(1));
+        assert_eq!(find_line(source, &["hooks"], "post-install"), Some(2));
+        assert_eq!(find_line(source, &["tool", "prp"], "python"), Some(5));
+    }
+
+    #[test]
+    fn split_key_parts() {
+        let split = |text| split_key(text).map(|(parts, rest)| (parts, rest.to_string()));
+        assert_eq!(
+            split(r#"a . "b.c".'d' = 1"#),
+            Some((vec!["a".into(), "b.c".into(), "d".into()], "= 1".into()))
+        );
+        assert_eq!(
+            split(r#""with \"quote\""]"#),
+            Some((vec![r#"with "quote""#.into()], "]".into()))
+        );
+        assert_eq!(split("= 1"), None);
+        assert_eq!(split(r#""unterminated"#), None);
+    }
+
+    #[test]
+    fn problems_are_located() {
+        let path = PathBuf::from("/home/u/.local/share/prp/projects.toml");
+        let source = "[\"/home/u/proj\"]\nvenv-nme = \"a\"\nauto-activate = \"yes\"\n";
+        let document: Document = source.parse().unwrap();
+        let layer = Layer {
+            origin: Origin::Projects(path),
+            table: document["/home/u/proj"].as_table().unwrap().clone(),
+            source: source.to_string(),
+            header: vec!["/home/u/proj".to_string()],
+        };
+
+        let mut problems = vec![];
+        validate_table(&layer, &layer.table, &mut vec![], &mut problems);
+        let messages: Vec<&str> = problems.iter().map(|p| p.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "/home/u/.local/share/prp/projects.toml:2: Unknown setting `venv-nme`, did you mean `venv-name`?",
+                "/home/u/.local/share/prp/projects.toml:3: `auto-activate` must be a boolean, not \"yes\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn check_value_by_kind() {
+        assert_eq!(check_value("venv-name", &item("\".venv\"")), Ok(()));
+        assert_eq!(check_value("venv-name", &item("1")), Err("a string".into()));
+        assert_eq!(check_value("auto-activate", &item("true")), Ok(()));
+        assert_eq!(
+            check_value("auto-activate", &item("\"true\"")),
+            Err("a boolean".into())
+        );
+        assert_eq!(check_value("lock-timeout", &item("30")), Ok(()));
+        assert_eq!(
+            check_value("lock-timeout", &item("-1")),
+            Err("a positive integer".into())
+        );
+        assert_eq!(check_value("seed-packages", &item("[\"ruff\"]")), Ok(()));
+        assert_eq!(
+            check_value("seed-packages", &item("[\"ruff\", 1]")),
+            Err("an array of strings".into())
+        );
+        assert_eq!(check_value("link-mode", &item("\"copy\"")), Ok(()));
+        assert_eq!(
+            check_value("link-mode", &item("\"symlink\"")),
+            Err("one of `copy`, `hardlink`, `reflink`".into())
+        );
+
+        // Unknown settings are reported by `find_key` instead.
+        assert_eq!(check_value("nonsense", &item("1")), Ok(()));
+    }
+
+    #[test]
+    fn check_value_parses_python_requests() {
+        for python in [
+            "3.11",
+            "pypy3.10",
+            ">=3.9,<3.12",
+            "python3",
+            "/usr/bin/python3",
+        ] {
+            assert_eq!(check_value("python", &item(&format!("{python:?}"))), Ok(()));
+        }
+        assert!(check_value("python", &item("\">=\"")).is_err());
+        assert!(check_value("python-path", &item("\">=\"")).is_err());
+        assert_eq!(check_value("python", &item("3")), Err("a string".into()));
+    }
+
+    #[test]
+    fn find_key_suggests_close_matches() {
+        assert_eq!(find_key("venv-name").unwrap().name, "venv-name");
+        assert_eq!(
+            find_key("hooks.post-create").unwrap().name,
+            "hooks.post-create"
+        );
+        assert_eq!(
+            find_key("venv_name")
+                .map(|k| k.name)
+                .unwrap_err()
+                .to_string(),
+            "Unknown setting `venv_name`, did you mean `venv-name`?"
+        );
+        assert_eq!(
+            find_key("zzz").map(|k| k.name).unwrap_err().to_string(),
+            "Unknown setting `zzz`"
+        );
+    }
+
+    #[test]
+    fn env_items_are_parsed_by_kind() {
+        for value in ["1", "true", "YES", "on"] {
+            assert_eq!(env_item(&Kind::Bool, value.into()).as_bool(), Some(true));
+        }
+        for value in ["0", "false", "No", "off", ""] {
+            assert_eq!(env_item(&Kind::Bool, value.into()).as_bool(), Some(false));
+        }
+        // Left as strings, for validation to report.
+        assert!(env_item(&Kind::Bool, "maybe".into()).is_str());
+        assert!(env_item(&Kind::Integer, "ten".into()).is_str());
+
+        assert_eq!(env_item(&Kind::Integer, "30".into()).as_integer(), Some(30));
+        assert_eq!(
+            env_item(&Kind::String, "3.11".into()).as_str(),
+            Some("3.11")
+        );
+
+        let list = env_item(&Kind::List, "pyproject.toml::setup.py".into());
+        let values: Vec<&str> = list
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|v| v.as_str())
+            .collect();
+        assert_eq!(values, ["pyproject.toml", "setup.py"]);
+    }
+
+    #[test]
+    fn lookup_dotted_keys() {
+        let document: Document = "[hooks]\npost-create = \"a\"\n".parse().unwrap();
+        let table = document.as_table();
+        assert_eq!(
+            lookup(table, "hooks.post-create").and_then(|v| v.as_str()),
+            Some("a")
+        );
+        assert!(lookup(table, "hooks.post-install").is_none());
+        assert!(lookup(table, "hooks").unwrap().is_table());
+    }
 }
@@ -0,0 +1,64 @@
+use std::ffi::OsStr;
+use std::path::Path;
+
+use super::{EnvChanges, ShellBackend, EVAL_MARKER};
+
+pub struct Fish;
+
+impl ShellBackend for Fish {
+    // `string collect` keeps the output's lines intact for `eval`.
+    fn init(&self, exe_name: &str, exe_path: &Path) -> String {
+        indoc::formatdoc!(
+            r#"
+            function {exe_name}
+              set -l output (command {exe_path} --shell fish $argv | string collect)
+              if string match -q -- '{EVAL_MARKER}*' "$output"
+                eval $output
+              else if test -n "$output"
+                printf '%s\n' $output
+              end
+            end"#,
+            exe_path = exe_path.to_string_lossy(),
+        )
+    }
+
+    fn activate(&self, env: &EnvChanges) -> String {
+        let set = env.set.iter().map(|(name, value)| {
+            // Fish treats `*PATH` variables as lists (i.e. as `fish_add_path`
+            // leaves `PATH`), exporting them joined with `:`.
+            if name.ends_with("PATH") {
+                let values: Vec<String> = std::env::split_paths(value)
+                    .map(|p| quote(p.as_os_str()))
+                    .collect();
+                format!("set -gx {name} {}", values.join(" "))
+            } else {
+                format!("set -gx {name} {}", quote(value))
+            }
+        });
+        let unset = env.unset.iter().map(|name| format!("set -e {name}"));
+        set.chain(unset).collect::<Vec<String>>().join("\n")
+    }
+
+    // The original prompt is run first, so that it sees the last `$status`.
+    fn prompt(&self) -> String {
+        indoc::indoc!(
+            r#"
+            if not functions -q _prp_fish_prompt
+              functions -c fish_prompt _prp_fish_prompt
+              function fish_prompt
+                set -l prompt (_prp_fish_prompt)
+                set -q VIRTUAL_ENV; and printf '(%s) ' (basename $VIRTUAL_ENV)
+                string join \n -- $prompt
+              end
+            end"#
+        )
+        .to_string()
+    }
+}
+
+/// Single-quotes `value` for fish, which (unlike POSIX shells) allows escapes
+/// within single quotes.
+fn quote(value: &OsStr) -> String {
+    let value = value.to_string_lossy();
+    format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+}
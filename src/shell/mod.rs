@@ -0,0 +1,321 @@
+use anyhow::Context;
+use clap::ValueEnum;
+use std::ffi::{OsStr, OsString};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::settings::Hook;
+use crate::venv::{Venv, VenvPaths};
+
+mod fish;
+mod nu;
+mod posix;
+mod xonsh;
+
+/// The environment from before `activate`, restored by `deactivate`.
+const OLD_PATH: &str = "_PRP_OLD_PATH";
+const OLD_VIRTUAL_ENV: &str = "_PRP_OLD_VIRTUAL_ENV";
+
+/// Begins output which `init`'s function should evaluate, rather than print.
+const EVAL_MARKER: &str = "# prp: eval";
+
+/// The shells `prp` can integrate with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ShellKind {
+    Bash,
+    Zsh,
+    Fish,
+    Elvish,
+    Powershell,
+    Nu,
+    Xonsh,
+}
+
+impl ShellKind {
+    /// The shell named by `$SHELL`, if it's a known one.
+    pub fn from_env() -> Option<Self> {
+        let shell = std::env::var_os("SHELL")?;
+        let name = Path::new(&shell).file_stem()?.to_str()?;
+        match name {
+            "pwsh" => Some(Self::Powershell),
+            name => Self::from_str(name, true).ok(),
+        }
+    }
+
+    /// The shell, as known to `clap_complete` (if it can generate completions
+    /// for it).
+    pub fn completions(&self) -> Option<clap_complete::Shell> {
+        match self {
+            Self::Bash => Some(clap_complete::Shell::Bash),
+            Self::Zsh => Some(clap_complete::Shell::Zsh),
+            Self::Fish => Some(clap_complete::Shell::Fish),
+            Self::Elvish => Some(clap_complete::Shell::Elvish),
+            Self::Powershell => Some(clap_complete::Shell::PowerShell),
+            Self::Nu | Self::Xonsh => None,
+        }
+    }
+
+    /// The shell's integration, if `prp` has one for it.
+    fn backend(&self) -> Option<Box<dyn ShellBackend>> {
+        match self {
+            Self::Bash | Self::Zsh => Some(Box::new(posix::Posix(*self))),
+            Self::Fish => Some(Box::new(fish::Fish)),
+            Self::Nu => Some(Box::new(nu::Nu)),
+            Self::Xonsh => Some(Box::new(xonsh::Xonsh)),
+            Self::Elvish | Self::Powershell => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ShellKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = self.to_possible_value().expect("no skipped variants");
+        write!(f, "{}", value.get_name())
+    }
+}
+
+/// The changes `activate`/`deactivate` make to the environment.
+#[derive(Debug, Default)]
+pub struct EnvChanges {
+    pub set: Vec<(&'static str, OsString)>,
+    pub unset: Vec<&'static str>,
+}
+
+impl EnvChanges {
+    fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.unset.is_empty()
+    }
+}
+
+/// The code a shell's integration evaluates, in that shell's syntax.
+pub trait ShellBackend {
+    /// Defines the `exe_name` function, which evaluates the code `prp` prints
+    /// for it (see `eval`), and passes along any other output.
+    fn init(&self, exe_name: &str, exe_path: &Path) -> String;
+
+    /// Marks `code` for `init`'s function to evaluate.
+    fn eval(&self, code: String) -> String {
+        format!("{EVAL_MARKER}\n{code}")
+    }
+
+    /// Applies the changes of activating a venv.
+    fn activate(&self, env: &EnvChanges) -> String;
+
+    /// Applies the changes of deactivating a venv.
+    fn deactivate(&self, env: &EnvChanges) -> String {
+        self.activate(env)
+    }
+
+    /// Prefixes the shell's prompt with the name of the active venv.
+    fn prompt(&self) -> String;
+}
+
+pub struct Shell {
+    exe_name: String,
+    exe_path: PathBuf,
+    backend: Option<Box<dyn ShellBackend>>,
+    pub kind: String,
+}
+
+impl Shell {
+    pub fn new(kind: ShellKind) -> anyhow::Result<Shell> {
+        let exe_path = std::env::current_exe()?;
+        let exe_name = exe_path
+            .file_name()
+            .context(format!("{exe_path:?} is has no file name"))?;
+
+        Ok(Self {
+            exe_path: exe_path.clone(),
+            exe_name: exe_name.to_string_lossy().to_string(),
+            backend: kind.backend(),
+            kind: kind.to_string(),
+        })
+    }
+
+    /// Whether `prp` can integrate with the shell, i.e. activate venvs in it.
+    pub fn is_integrated(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    fn backend(&self) -> anyhow::Result<&dyn ShellBackend> {
+        match &self.backend {
+            Some(backend) => Ok(backend.as_ref()),
+            None => anyhow::bail!("Shell integration is not supported for {}", self.kind),
+        }
+    }
+
+    pub fn init(&self) -> anyhow::Result<()> {
+        let backend = self.backend()?;
+        println!("{}", backend.init(&self.exe_name, &self.exe_path));
+        Ok(())
+    }
+
+    pub fn init_prompt(&self) -> anyhow::Result<()> {
+        let backend = self.backend()?;
+        println!("{}", backend.eval(backend.prompt()));
+        Ok(())
+    }
+
+    pub fn activate(&self, venv: &Venv) -> anyhow::Result<()> {
+        let backend = self.backend()?;
+        if venv.exists() {
+            venv.run_hook(Hook::PreActivate)?;
+
+            // Remember the environment from before the first activation, for
+            // `deactivate`. Later activations replace the active venv instead.
+            let mut env = EnvChanges::default();
+            if std::env::var_os(OLD_PATH).is_none() {
+                env.set
+                    .push((OLD_PATH, std::env::var_os("PATH").unwrap_or_default()));
+                if let Some(virtual_env) = std::env::var_os("VIRTUAL_ENV") {
+                    env.set.push((OLD_VIRTUAL_ENV, virtual_env));
+                }
+            }
+
+            env.set
+                .push(("VIRTUAL_ENV", venv.paths.path.clone().into_os_string()));
+            env.set
+                .push(("PATH", Shell::extend_path(&venv.paths.scripts_path)));
+            println!("{}", backend.eval(backend.activate(&env)));
+
+            venv.run_hook(Hook::PostActivate)?;
+        }
+        Ok(())
+    }
+
+    /// Restores the environment from before `activate`.
+    pub fn deactivate(&self) -> anyhow::Result<()> {
+        let backend = self.backend()?;
+        let mut env = EnvChanges::default();
+        match std::env::var_os(OLD_PATH) {
+            Some(old_path) => {
+                env.set.push(("PATH", old_path));
+                env.unset.push(OLD_PATH);
+                match std::env::var_os(OLD_VIRTUAL_ENV) {
+                    Some(virtual_env) => {
+                        env.set.push(("VIRTUAL_ENV", virtual_env));
+                        env.unset.push(OLD_VIRTUAL_ENV);
+                    }
+                    None => env.unset.push("VIRTUAL_ENV"),
+                }
+            }
+            // A venv activated by other means, i.e. `bin/activate`.
+            None => {
+                if let Some(virtual_env) = std::env::var_os("VIRTUAL_ENV") {
+                    env.set.push(("PATH", Self::active_path(&virtual_env)));
+                    env.unset.push("VIRTUAL_ENV");
+                }
+            }
+        }
+
+        if env.is_empty() {
+            eprintln!("No venv is active");
+        } else {
+            println!("{}", backend.eval(backend.deactivate(&env)));
+        }
+        Ok(())
+    }
+
+    pub fn enter(&self, venv: &Venv) -> anyhow::Result<()> {
+        let path = Self::extend_path(&venv.paths.scripts_path);
+        Err(anyhow::Error::from(
+            Command::new(&self.kind)
+                .env("VIRTUAL_ENV", &venv.paths.path)
+                .env("PATH", path)
+                .exec(),
+        ))
+    }
+
+    /// `PATH`, with `path` first, and without the scripts of any already
+    /// active venv.
+    pub fn extend_path(path: &Path) -> OsString {
+        let active = std::env::var_os("VIRTUAL_ENV").map(|v| VenvPaths::new(Path::new(&v)));
+
+        let mut paths = vec![];
+        if let Some(path_var) = std::env::var_os("PATH") {
+            let existing_paths = std::env::split_paths(&path_var);
+            let existing_paths: Vec<_> = existing_paths
+                .filter(|i| i != path)
+                .filter(|i| {
+                    active
+                        .as_ref()
+                        .map(|a| i != &a.scripts_path)
+                        .unwrap_or(true)
+                })
+                .collect();
+
+            paths.push(path.to_path_buf());
+            paths.extend(existing_paths);
+        } else {
+            paths.push(path.to_path_buf());
+        };
+
+        let path = std::env::join_paths(paths).ok();
+        path.unwrap_or("".into())
+    }
+
+    /// `PATH`, without the scripts of the venv at `virtual_env`.
+    fn active_path(virtual_env: &OsStr) -> OsString {
+        let scripts_path = VenvPaths::new(Path::new(virtual_env)).scripts_path;
+        let path_var = std::env::var_os("PATH").unwrap_or_default();
+        let paths = std::env::split_paths(&path_var).filter(|i| i != &scripts_path);
+        std::env::join_paths(paths).unwrap_or_default()
+    }
+
+    pub fn run(&self, venv: &Venv, command: Option<&str>, args: &[String]) -> anyhow::Result<()> {
+        match command {
+            Some(command) => {
+                let bin = venv.paths.script(command);
+                if !&bin.exists() {
+                    anyhow::bail!("No such command: {command}");
+                }
+
+                let stderr = os_pipe::dup_stderr()?;
+                let mut child = Command::new(bin.to_string_lossy().as_ref())
+                    .args(args)
+                    .env("VIRTUAL_ENV", &venv.paths.path)
+                    .stdout(stderr)
+                    .spawn()?;
+
+                child.wait()?;
+            }
+            None => {
+                let scripts = venv
+                    .paths
+                    .scripts_path
+                    .read_dir()?
+                    .filter_map(Result::ok)
+                    .map(|f| f.file_name().to_string_lossy().to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                eprintln!("Available scripts: {scripts}");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn exec(&self, venv: &Venv, command: &str, args: &[String]) -> anyhow::Result<()> {
+        let args = [&["-c".to_string(), command.to_string()], args].concat();
+
+        let stderr = os_pipe::dup_stderr()?;
+        let mut child = Command::new(&self.kind)
+            .args(args)
+            .env("VIRTUAL_ENV", &venv.paths.path)
+            .stdout(stderr)
+            .spawn()?;
+
+        child.wait()?;
+
+        Ok(())
+    }
+
+    pub fn prompt(&self, venv: &Venv) -> anyhow::Result<()> {
+        Err(anyhow::Error::from(
+            Command::new(&venv.paths.python_path)
+                .env("VIRTUAL_ENV", &venv.paths.path)
+                .exec(),
+        ))
+    }
+}
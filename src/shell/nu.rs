@@ -0,0 +1,71 @@
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+
+use super::{EnvChanges, ShellBackend, EVAL_MARKER};
+
+/// Nushell can't evaluate code at runtime, so `prp` instead prints the changes
+/// as JSON (after the eval marker, which is a comment in nushell), for the
+/// `def --env` wrapper to apply with `load-env`/`hide-env`.
+pub struct Nu;
+
+impl ShellBackend for Nu {
+    fn init(&self, exe_name: &str, exe_path: &Path) -> String {
+        indoc::formatdoc!(
+            r#"
+            def --env --wrapped {exe_name} [...args] {{
+              let output = (^{exe_path} --shell nu ...$args)
+              let changes = if ($output | str starts-with {marker}) {{
+                try {{ $output | lines | skip 1 | str join "\n" | from json }} catch {{ null }}
+              }}
+              if ($changes | describe | str starts-with 'record') {{
+                load-env $changes.set
+                if ($changes.unset | is-not-empty) {{
+                  hide-env --ignore-errors ...$changes.unset
+                }}
+              }} else if ($output | is-not-empty) {{
+                print $output
+              }}
+            }}"#,
+            exe_path = Value::from(exe_path.to_string_lossy()),
+            marker = Value::from(format!("{EVAL_MARKER}\n")),
+        )
+    }
+
+    fn activate(&self, env: &EnvChanges) -> String {
+        let mut set = Map::new();
+        for (name, value) in &env.set {
+            // `$env.PATH` is a list.
+            let value = if *name == "PATH" {
+                std::env::split_paths(value)
+                    .map(|p| Value::from(p.to_string_lossy()))
+                    .collect()
+            } else {
+                Value::from(value.to_string_lossy())
+            };
+            set.insert(name.to_string(), value);
+        }
+        json!({ "set": set, "unset": env.unset }).to_string()
+    }
+
+    fn prompt(&self) -> String {
+        indoc::indoc!(
+            r#"
+            let prp_prompt = $env.PROMPT_COMMAND?
+            $env.PROMPT_COMMAND = {||
+              let venv = if ($env.VIRTUAL_ENV? | is-empty) {
+                ''
+              } else {
+                ['(' ($env.VIRTUAL_ENV | path basename) ') '] | str join
+              }
+              let prompt = if ($prp_prompt | describe | str starts-with 'closure') {
+                do $prp_prompt
+              } else {
+                $prp_prompt | default ''
+              }
+              $venv + $prompt
+            }"#
+        )
+        .to_string()
+    }
+}
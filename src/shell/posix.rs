@@ -0,0 +1,58 @@
+use std::ffi::OsStr;
+use std::path::Path;
+
+use super::{EnvChanges, ShellBackend, ShellKind, EVAL_MARKER};
+
+/// bash/zsh-like shells; which one, so that the integration keeps asking for
+/// its code regardless of `$SHELL` (i.e. bash started from a fish login shell).
+pub struct Posix(pub ShellKind);
+
+impl ShellBackend for Posix {
+    fn init(&self, exe_name: &str, exe_path: &Path) -> String {
+        indoc::formatdoc!(
+            r#"
+            function {exe_name} {{
+              local output status
+              output="$(command {exe_path} --shell {kind} "$@")"
+              status=$?
+              case "$output" in
+                '{EVAL_MARKER}'*) eval "$output" ;;
+                ?*) printf '%s\n' "$output" ;;
+              esac
+              return $status
+            }}"#,
+            exe_path = exe_path.to_string_lossy(),
+            kind = self.0,
+        )
+    }
+
+    fn activate(&self, env: &EnvChanges) -> String {
+        let set = env
+            .set
+            .iter()
+            .map(|(name, value)| format!("export {name}={}", quote(value)));
+        let unset = env.unset.iter().map(|name| format!("unset {name}"));
+        set.chain(unset).collect::<Vec<String>>().join("\n")
+    }
+
+    // zsh only expands `$(...)` in the prompt with `PROMPT_SUBST`.
+    fn prompt(&self) -> String {
+        indoc::indoc!(
+            r#"
+            _prp_prompt() {
+              [ -n "$VIRTUAL_ENV" ] && printf '(%s) ' "${VIRTUAL_ENV##*/}"
+            }
+            [ -n "$ZSH_VERSION" ] && setopt PROMPT_SUBST
+            case "$PS1" in
+              *_prp_prompt*) ;;
+              *) PS1='$(_prp_prompt)'"$PS1" ;;
+            esac"#
+        )
+        .to_string()
+    }
+}
+
+/// Single-quotes `value` for the shell.
+fn quote(value: &OsStr) -> String {
+    format!("'{}'", value.to_string_lossy().replace('\'', r"'\''"))
+}
@@ -0,0 +1,59 @@
+use std::ffi::OsStr;
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::{EnvChanges, ShellBackend, EVAL_MARKER};
+
+pub struct Xonsh;
+
+impl ShellBackend for Xonsh {
+    fn init(&self, exe_name: &str, exe_path: &Path) -> String {
+        indoc::formatdoc!(
+            r#"
+            def _{exe_name}(args):
+                output = $({exe_path} --shell xonsh @(args))
+                if output.startswith('{EVAL_MARKER}'):
+                    execx(output)
+                elif output:
+                    print(output, end='')
+            aliases[{name}] = _{exe_name}"#,
+            exe_path = quote(exe_path.as_os_str()),
+            name = quote(OsStr::new(exe_name)),
+        )
+    }
+
+    fn activate(&self, env: &EnvChanges) -> String {
+        let mut lines = vec![];
+        for (name, value) in &env.set {
+            // `$PATH` is a list.
+            if *name == "PATH" {
+                let paths: Vec<String> = std::env::split_paths(value)
+                    .map(|p| quote(p.as_os_str()))
+                    .collect();
+                lines.push(format!("$PATH = [{}]", paths.join(", ")));
+            } else {
+                lines.push(format!("${name} = {}", quote(value)));
+            }
+        }
+        for name in &env.unset {
+            lines.push(format!("${{...}}.pop({}, None)", quote(OsStr::new(name))));
+        }
+        lines.join("\n")
+    }
+
+    // xonsh's `{env_name}` prompt field shows the active venv.
+    fn prompt(&self) -> String {
+        indoc::indoc!(
+            r#"
+            if isinstance($PROMPT, str) and '{env_name}' not in $PROMPT:
+                $PROMPT = '{env_name}' + $PROMPT"#
+        )
+        .to_string()
+    }
+}
+
+/// Quotes `value` as a python string; JSON strings are valid python strings.
+fn quote(value: &OsStr) -> String {
+    Value::from(value.to_string_lossy()).to_string()
+}
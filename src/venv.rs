@@ -1,15 +1,20 @@
 use corpus::{builder, Corpus, RootLocation};
-use indoc::formatdoc;
-use std::fs::File;
-use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Output};
+use std::time::{Duration, SystemTime};
 use symlink::symlink_file;
 
+use crate::distribution::Distribution;
+use crate::lock::Lock;
 use crate::package_specifier::PackageSpecifier;
-use crate::python::Python;
-use crate::settings::{Settings, Strategy};
+use crate::posy::package_name::PackageName;
+use crate::python::{find_python, Python, PythonRequest};
+use crate::pyvenv_cfg::PyvenvCfg;
+use crate::settings::{Hook, Hooks, LinkMode, Origin, Settings, Strategy};
+use crate::shell::Shell;
+use crate::wheel::{relative_path, Wheel, WheelCache};
 
+#[derive(Clone)]
 pub struct VenvPaths {
     pub path: PathBuf,
 
@@ -21,6 +26,9 @@ pub struct VenvPaths {
     pub python_path: PathBuf,
     pub pip_path: PathBuf,
     pub pyvenv_cfg: PathBuf,
+
+    /// Touched whenever prp uses the venv.
+    pub last_used: PathBuf,
 }
 
 impl VenvPaths {
@@ -36,6 +44,7 @@ impl VenvPaths {
             python_path: scripts_path.join("python"),
             pip_path: scripts_path.join("pip"),
             pyvenv_cfg: path.join("pyvenv.cfg"),
+            last_used: path.join(".prp-last-used"),
         }
     }
 
@@ -54,6 +63,21 @@ impl VenvPaths {
             minor = python.minor
         ))
     }
+
+    /// Every `lib/python*/site-packages` in the venv, regardless of version.
+    pub fn site_packages_paths(&self) -> Vec<PathBuf> {
+        let Ok(entries) = self.lib_path.read_dir() else {
+            return vec![];
+        };
+
+        entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("python"))
+            .map(|entry| entry.path().join("site-packages"))
+            .filter(|path| path.is_dir())
+            .collect()
+    }
+
     pub fn python_path_major(&self, python: &Python) -> PathBuf {
         self.scripts_path
             .join(format!("python{major}", major = python.major))
@@ -76,47 +100,163 @@ impl VenvPaths {
         ))
     }
 
-    pub fn pyvenv_cfg_content(&self, python: &Python) -> String {
-        formatdoc!(
-            r#"
-                home = {scripts_path}
-                include-system-site-packages = false
-                version = {major}.{minor}.{patch}
-                executable = {python_executable}
-                "#,
-            major = python.major,
-            minor = python.minor,
-            patch = python.patch,
-            scripts_path = self.scripts_path.to_string_lossy(),
-            python_executable = python.exe_path.to_string_lossy(),
-        )
+    /// The venv's `pyvenv.cfg`, updated to describe the given interpreter and
+    /// options. Any other keys already in the file are preserved.
+    pub fn pyvenv_cfg(&self, python: &Python, options: &VenvOptions) -> PyvenvCfg {
+        let mut cfg = self.read_cfg();
+        cfg.set("home", &self.scripts_path.to_string_lossy());
+        cfg.set(
+            "include-system-site-packages",
+            &options.system_site_packages.to_string(),
+        );
+        cfg.set(
+            "version",
+            &format!("{}.{}.{}", python.major, python.minor, python.patch),
+        );
+        cfg.set("executable", &python.exe_path.to_string_lossy());
+
+        match &options.prompt {
+            Some(prompt) => cfg.set("prompt", prompt),
+            None => cfg.remove("prompt"),
+        }
+
+        // Options which only affect how prp (re)creates the venv.
+        for (key, enabled) in [
+            ("prp-copies", options.copies),
+            ("prp-without-pip", options.without_pip),
+        ] {
+            if enabled {
+                cfg.set(key, "true");
+            } else {
+                cfg.remove(key);
+            }
+        }
+        cfg
+    }
+
+    /// The venv's `pyvenv.cfg`, or an empty one if it doesn't exist.
+    pub fn read_cfg(&self) -> PyvenvCfg {
+        PyvenvCfg::read(&self.pyvenv_cfg).unwrap_or_default()
+    }
+
+    /// The options the venv was created with, as recorded in its `pyvenv.cfg`.
+    pub fn read_options(&self) -> VenvOptions {
+        let cfg = self.read_cfg();
+        VenvOptions {
+            system_site_packages: cfg.include_system_site_packages(),
+            prompt: cfg.prompt(),
+            copies: cfg.get("prp-copies") == Some("true"),
+            without_pip: cfg.get("prp-without-pip") == Some("true"),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct VenvOptions {
+    pub fix: bool,
+    pub clear: bool,
+    pub system_site_packages: bool,
+    pub prompt: Option<String>,
+    pub copies: bool,
+    pub without_pip: bool,
+
+    /// Whether to install the `seed-packages` (those which are missing).
+    pub seed: bool,
+}
+
+impl VenvOptions {
+    /// Combines explicitly requested options with those the venv was
+    /// previously created with, so that `--fix` recreates it the same way.
+    /// (`--clear` doesn't merge, so it's how to drop a recorded option.)
+    pub fn merge(&self, persisted: &VenvOptions) -> VenvOptions {
+        VenvOptions {
+            fix: self.fix,
+            clear: self.clear,
+            system_site_packages: self.system_site_packages || persisted.system_site_packages,
+            prompt: self.prompt.clone().or_else(|| persisted.prompt.clone()),
+            copies: self.copies || persisted.copies,
+            without_pip: self.without_pip || persisted.without_pip,
+            seed: self.seed,
+        }
     }
 }
 
+#[derive(Clone)]
 pub struct Venv {
-    pub python_path: PathBuf,
+    pub python: PythonRequest,
+    pub python_search_path: Vec<PathBuf>,
     pub paths: VenvPaths,
     pub name: String,
+
+    /// The project the venv is for, if any.
+    pub project: Option<PathBuf>,
+
+    pub wheel_cache: WheelCache,
+    pub link_mode: LinkMode,
+    pub pip_wheel: Option<PathBuf>,
+    pub seed_packages: Vec<String>,
+
+    /// A requirements file, relative to the project, for its new venvs.
+    pub requirements: Option<PathBuf>,
+    pub hooks: Hooks,
+
+    lock_path: PathBuf,
+    lock_timeout: Duration,
 }
 
 impl Venv {
     pub fn from_current_dir(settings: &Settings) -> anyhow::Result<Venv> {
-        let current_dir = std::env::current_dir()?;
-        let dir = find_project_root(&current_dir, &settings.project_root);
+        Self::from_current_dir_with_name(settings, &settings.venv_name)
+    }
+
+    /// The current project's venv named `name`, rather than the configured one.
+    pub fn from_current_dir_with_name(settings: &Settings, name: &str) -> anyhow::Result<Venv> {
+        let dir = settings.project_dir.clone();
+
+        let mut python = settings.python.clone();
+        // Only an explicit choice of python for this project overrides its own.
+        if matches!(
+            settings.origin("python"),
+            Origin::Default | Origin::Global(_)
+        ) {
+            if let Some(project_python) = PythonRequest::from_project(&dir) {
+                python = project_python;
+            }
+        }
 
         let path = match settings.strategy {
-            Strategy::Local => dir,
-            Strategy::Central => {
-                let corpus: Corpus = builder()
-                    .relative_to_home()?
-                    .with_root(RootLocation::XDGData)
-                    .with_name(&settings.name)
-                    .build()?;
-
-                corpus.path(dir.as_path())
+            Strategy::Local => dir.join(name),
+            Strategy::Central => central_path(settings, &dir)?.join(name),
+            Strategy::Named => settings.named_venvs_path.join(name),
+            Strategy::Git => match git_head(&dir) {
+                Some((root, branch)) => central_path(settings, &root)?
+                    .join(branch.replace('/', "-"))
+                    .join(name),
+                // Outside of a repository, there's no branch to key on.
+                None => central_path(settings, &dir)?.join(name),
+            },
+            Strategy::Template => {
+                let Some(template) = &settings.venv_path else {
+                    anyhow::bail!("The `template` strategy requires the `venv-path` setting");
+                };
+                render_template(
+                    template,
+                    &dir,
+                    name,
+                    &python,
+                    &settings.python_search_dirs(),
+                )?
             }
         };
-        Ok(Venv::new(settings, path, &settings.venv_name))
+
+        let mut venv = Venv::at(settings, path, name);
+        venv.python = python;
+        // Named venvs are shared, rather than belonging to any one project.
+        if !matches!(settings.strategy, Strategy::Named) {
+            venv.project = Some(dir);
+        }
+        Ok(venv)
     }
 
     pub fn from_package_name(settings: &Settings, package_name: &str) -> Venv {
@@ -124,30 +264,138 @@ impl Venv {
     }
 
     pub fn new(settings: &Settings, root_path: PathBuf, name: &str) -> Self {
-        let path = root_path.join(name);
+        Self::at(settings, root_path.join(name), name)
+    }
+
+    /// A venv at `path`, which needn't be named after it.
+    pub fn at(settings: &Settings, path: PathBuf, name: &str) -> Self {
         Self {
-            python_path: settings.python_path.clone(),
+            python: settings.python.clone(),
+            python_search_path: settings.python_search_dirs(),
             paths: VenvPaths::new(&path),
             name: name.to_string(),
+            project: None,
+            wheel_cache: WheelCache::new(&settings.wheel_cache_path),
+            link_mode: settings.link_mode,
+            pip_wheel: settings.pip_wheel.clone(),
+            seed_packages: settings.seed_packages.clone(),
+            requirements: settings.requirements.clone(),
+            hooks: settings.hooks.clone(),
+            lock_path: settings
+                .lock_dir
+                .join(format!("{}.lock", short_hash(&path))),
+            lock_timeout: Duration::from_secs(settings.lock_timeout),
         }
     }
 
+    /// Locks the venv against other prp processes changing it (i.e. creating,
+    /// installing into, or deleting it) until the lock is dropped.
+    pub fn lock(&self) -> anyhow::Result<Lock> {
+        Lock::acquire(
+            &self.lock_path,
+            &self.paths.path.to_string_lossy(),
+            self.lock_timeout,
+        )
+    }
+
+    /// Locks the venv, unless another process already has; in which case,
+    /// returns that process's PID.
+    pub fn try_lock(&self) -> anyhow::Result<Result<Lock, String>> {
+        Lock::try_acquire(&self.lock_path)
+    }
+
     pub fn exists(&self) -> bool {
         self.paths.exists()
     }
 
-    pub fn create(&mut self, fix: bool) -> anyhow::Result<()> {
-        if !fix && self.paths.exists() {
-            return Ok(());
+    /// The venv's `pyvenv.cfg`, regardless of which tool created the venv.
+    pub fn cfg(&self) -> PyvenvCfg {
+        self.paths.read_cfg()
+    }
+
+    /// Creates the venv, or repairs or fixes an existing one as `options` say.
+    /// A new venv (including by `--clear`, or by repairing a broken venv) runs
+    /// the `post-create` hook.
+    pub fn create(&mut self, options: &VenvOptions) -> anyhow::Result<()> {
+        if self.paths.exists() && !options.clear && options.fix {
+            if let Some(from) = self.moved_from() {
+                self.relocate(&from)?;
+            }
+        }
+
+        if self.paths.exists() && !options.clear {
+            let health = self.health();
+            if !health.is_healthy() {
+                if !options.fix {
+                    eprintln!(
+                        "Warning: {} is broken, leaving it as it is:\n{}",
+                        self.paths.path.to_string_lossy(),
+                        health
+                    );
+                    return Ok(());
+                }
+
+                eprintln!(
+                    "Repairing broken venv at {}:\n{}",
+                    self.paths.path.to_string_lossy(),
+                    health
+                );
+                return self.repair(options);
+            }
+
+            if !options.fix {
+                return Ok(());
+            }
         }
 
-        let python = Python::detect(&self.python_path)?;
+        let python = find_python(&self.python, &self.python_search_path)?;
 
-        let site_packages = self.paths.site_packages_path(&python);
+        // `--clear` starts over, rather than recreating the venv as it was.
+        let options = if options.clear {
+            options.clone()
+        } else {
+            options.merge(&self.paths.read_options())
+        };
+
+        let created = !self.paths.exists() || options.clear;
+        if !created {
+            // Fixing an existing venv happens in place.
+            self.build(&self.paths, &python, &options)?;
+        } else {
+            // Otherwise, the venv is assembled beside its final location, and
+            // only then moved into place; so that it's never seen half-created,
+            // and a failure leaves any existing venv as it was.
+            self.replace(&python, &options, &[])?;
+        }
+
+        self.finish(&options, created)
+    }
+
+    /// Completes `create`; running the `post-create` hook for a new venv,
+    /// before installing the `seed-packages` (and so the `post-install` hook).
+    fn finish(&self, options: &VenvOptions, created: bool) -> anyhow::Result<()> {
+        if created {
+            self.run_hook(Hook::PostCreate)?;
+        }
+        if !options.without_pip && options.seed {
+            self.seed(created);
+        }
+        Ok(())
+    }
+
+    /// Lays out a venv at `paths` (either this venv's, or where it's being
+    /// assembled), and bootstraps pip into it.
+    fn build(
+        &self,
+        paths: &VenvPaths,
+        python: &Python,
+        options: &VenvOptions,
+    ) -> anyhow::Result<()> {
+        let site_packages = paths.site_packages_path(python);
         let mut required_paths = vec![
-            &self.paths.path,
-            &self.paths.scripts_path,
-            &self.paths.include_path,
+            &paths.path,
+            &paths.scripts_path,
+            &paths.include_path,
             &site_packages,
         ];
 
@@ -155,7 +403,7 @@ impl Venv {
             && cfg!(target_family = "unix")
             && cfg!(not(target_os = "macos"))
         {
-            required_paths.push(&self.paths.lib64_path);
+            required_paths.push(&paths.lib64_path);
         }
 
         for path in required_paths {
@@ -164,31 +412,543 @@ impl Venv {
             }
         }
 
-        create_symlink(&python.exe_path, &self.paths.python_path)?;
-        create_symlink(&python.exe_path, &self.paths.python_path_major(&python))?;
-        create_symlink(&python.exe_path, &self.paths.python_path_minor(&python))?;
-        create_symlink(&python.exe_path, &self.paths.python_path_patch(&python))?;
+        let link = if options.copies {
+            create_copy
+        } else {
+            create_symlink
+        };
+        link(&python.exe_path, &paths.python_path)?;
+        link(&python.exe_path, &paths.python_path_major(python))?;
+        link(&python.exe_path, &paths.python_path_minor(python))?;
+        link(&python.exe_path, &paths.python_path_patch(python))?;
+
+        let mut cfg = paths.pyvenv_cfg(python, options);
+        if let Some(project) = &self.project {
+            cfg.set("prp-project", &project.to_string_lossy());
+        }
+        cfg.write(&paths.pyvenv_cfg)?;
+
+        if !options.without_pip {
+            let venv = Venv {
+                paths: paths.clone(),
+                ..self.clone()
+            };
+            venv.ensure_pip(python)?;
+        }
+        Ok(())
+    }
+
+    /// Builds a new venv beside this one, with the given distributions
+    /// installed into it, and then moves it into place; so that it's never
+    /// seen half-created, and a failure leaves any existing venv as it was.
+    fn replace(
+        &self,
+        python: &Python,
+        options: &VenvOptions,
+        distributions: &[Distribution],
+    ) -> anyhow::Result<()> {
+        let staging = self.sibling_paths("staging")?;
+        let staged = Venv {
+            paths: staging.clone(),
+            ..self.clone()
+        };
+        let built = self.build(&staging, python, options).and_then(|_| {
+            let failed = staged.reinstall(distributions);
+            if !failed.is_empty() {
+                anyhow::bail!(
+                    "Failed to reinstall {}, so {} was left as it was \
+                     (`prp venv --clear` recreates it without them)",
+                    failed.join(", "),
+                    self.paths.path.to_string_lossy()
+                );
+            }
+            self.swap_in(&staging)
+        });
+        if let Err(e) = built {
+            if staging.exists() {
+                std::fs::remove_dir_all(&staging.path)?;
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// A location beside the venv, i.e. `.{name}.staging` to assemble it in,
+    /// which is cleared of anything left there by an earlier failure.
+    fn sibling_paths(&self, suffix: &str) -> anyhow::Result<VenvPaths> {
+        let name = self.paths.path.file_name().unwrap_or_default();
+        let paths = VenvPaths::new(
+            &self
+                .paths
+                .path
+                .with_file_name(format!(".{}.{suffix}", name.to_string_lossy())),
+        );
+        if paths.exists() {
+            std::fs::remove_dir_all(&paths.path)?;
+        }
+        Ok(paths)
+    }
+
+    /// Moves the venv assembled at `staging` into place, replacing any existing
+    /// venv only once the new one is there.
+    fn swap_in(&self, staging: &VenvPaths) -> anyhow::Result<()> {
+        let staged = Venv {
+            paths: staging.clone(),
+            ..self.clone()
+        };
+        staged.rewrite_paths(
+            &staging.path.to_string_lossy(),
+            &self.paths.path.to_string_lossy(),
+        )?;
+
+        if !self.paths.exists() {
+            std::fs::rename(&staging.path, &self.paths.path)?;
+            return Ok(());
+        }
+
+        let old = self.sibling_paths("old")?;
+        std::fs::rename(&self.paths.path, &old.path)?;
+        if let Err(e) = std::fs::rename(&staging.path, &self.paths.path) {
+            std::fs::rename(&old.path, &self.paths.path)?;
+            return Err(e.into());
+        }
+        std::fs::remove_dir_all(&old.path)?;
+        Ok(())
+    }
+
+    /// Checks for the ways a venv commonly breaks, i.e. the python it was
+    /// created from having been upgraded or removed.
+    pub fn health(&self) -> VenvHealth {
+        let mut issues = vec![];
+
+        if let Ok(entries) = self.paths.scripts_path.read_dir() {
+            for entry in entries.filter_map(Result::ok) {
+                let path = entry.path();
+                let is_python = entry.file_name().to_string_lossy().starts_with("python");
+                if is_python && path.is_symlink() && !path.exists() {
+                    issues.push(format!("{} is a dangling symlink", path.to_string_lossy()));
+                }
+            }
+        }
+        if !self.paths.python_path.exists() {
+            issues.push("The venv has no python interpreter".to_string());
+        }
+
+        let cfg = self.paths.read_cfg();
+        let recorded_version = cfg.version();
+        match cfg.executable() {
+            None => issues.push("pyvenv.cfg does not record an executable".to_string()),
+            Some(executable) => match Python::detect(&executable) {
+                Err(_) => issues.push(format!(
+                    "The base interpreter {} no longer exists",
+                    executable.to_string_lossy()
+                )),
+                Ok(python) => {
+                    let version = format!("{}.{}.{}", python.major, python.minor, python.patch);
+                    if recorded_version.as_ref() != Some(&version) {
+                        issues.push(format!(
+                            "pyvenv.cfg records version {}, but {} is {version}",
+                            recorded_version.as_deref().unwrap_or("<none>"),
+                            executable.to_string_lossy()
+                        ));
+                    }
+
+                    let site_packages = self.paths.site_packages_path(&python);
+                    if !site_packages.exists() {
+                        issues.push(format!(
+                            "{} does not exist",
+                            site_packages.to_string_lossy()
+                        ));
+                    }
+                }
+            },
+        }
+
+        VenvHealth { issues }
+    }
+
+    /// Recreates the venv from scratch with whatever had been installed into
+    /// it, only replacing the broken venv once that has succeeded.
+    fn repair(&self, options: &VenvOptions) -> anyhow::Result<()> {
+        let distributions: Vec<Distribution> = self
+            .installed_distributions()
+            .into_iter()
+            .filter(|d| !d.name.eq_ignore_ascii_case("pip"))
+            .collect();
+
+        let python = find_python(&self.python, &self.python_search_path)?;
+        let options = VenvOptions {
+            fix: false,
+            clear: false,
+            ..options.merge(&self.paths.read_options())
+        };
+        self.replace(&python, &options, &distributions)?;
+        self.finish(&options, true)
+    }
+
+    /// Where the venv was created, if it has since been moved; judging by the
+    /// shebangs of its scripts. Only shebangs naming a venv of the same name
+    /// (`<from>/bin/python...`) count, rather than any interpreter's.
+    pub fn moved_from(&self) -> Option<PathBuf> {
+        let name = self.paths.path.file_name()?;
+        self.paths
+            .scripts_path
+            .read_dir()
+            .ok()?
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+            .filter_map(|entry| {
+                let content = std::fs::read(entry.path()).ok()?;
+                let line = content.split(|b| *b == b'\n').next()?;
+                let interpreter = std::str::from_utf8(line).ok()?.strip_prefix("#!")?;
+                let (venv, python) = interpreter.trim().rsplit_once("/bin/")?;
+                let version = python.strip_prefix("python")?;
+                version
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || c == '.')
+                    .then(|| PathBuf::from(venv))
+            })
+            .find(|venv| venv.file_name() == Some(name))
+            .filter(|from| *from != self.paths.path)
+    }
+
+    /// Rewrites the absolute paths which refer to the venv's (or its project's)
+    /// previous location, `from`: script shebangs, `pyvenv.cfg`, `.pth` files,
+    /// and dist-info `RECORD` and (editable installs') `direct_url.json` files.
+    /// Returns the rewritten files.
+    pub fn relocate(&self, from: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let (old, new) = moved_roots(from, &self.paths.path);
+        eprintln!(
+            "Relocating venv from {} to {}",
+            from.to_string_lossy(),
+            self.paths.path.to_string_lossy()
+        );
+        self.rewrite_paths(&old, &new)
+    }
+
+    /// Rewrites the absolute paths beneath `old` to be beneath `new` instead,
+    /// in the files `relocate` describes. Returns the rewritten files.
+    fn rewrite_paths(&self, old: &str, new: &str) -> anyhow::Result<Vec<PathBuf>> {
+        let mut files = vec![self.paths.pyvenv_cfg.clone()];
+        if let Ok(entries) = self.paths.scripts_path.read_dir() {
+            files.extend(
+                entries
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+                    .map(|entry| entry.path()),
+            );
+        }
+
+        let site_packages_paths = self.paths.site_packages_paths();
+        let mut dist_infos = vec![];
+        for site_packages in &site_packages_paths {
+            let Ok(entries) = site_packages.read_dir() else {
+                continue;
+            };
+            for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+                match path.extension().and_then(|e| e.to_str()) {
+                    Some("pth") => files.push(path),
+                    Some("dist-info") => {
+                        files.push(path.join("direct_url.json"));
+                        dist_infos.push(path);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut rewritten = vec![];
+        for file in files {
+            // Binary files (i.e. a copied interpreter) can't be rewritten.
+            let Ok(content) = std::fs::read_to_string(&file) else {
+                continue;
+            };
+            if let Some(content) = replace_path(&content, old, new) {
+                replace_file(&file, content.as_bytes())?;
+                rewritten.push(file);
+            }
+        }
+
+        // Paths in RECORD are mostly relative to site-packages, but the hashes
+        // of rewritten files are no longer accurate.
+        for dist_info in dist_infos {
+            let record = dist_info.join("RECORD");
+            let Ok(rows) = crate::wheel::read_record(&record) else {
+                continue;
+            };
+            let Some(site_packages) = dist_info.parent() else {
+                continue;
+            };
+
+            let mut changed = false;
+            let mut updated = vec![];
+            for mut row in rows {
+                if let Some(path) = replace_path(&row.path, old, new) {
+                    row.path = path;
+                    changed = true;
+                }
+
+                let path = Path::new(&row.path);
+                let is_rewritten = rewritten
+                    .iter()
+                    .any(|r| relative_path(r, site_packages) == path || r == path);
+                if is_rewritten && !(row.hash.is_empty() && row.size.is_empty()) {
+                    row.hash.clear();
+                    row.size.clear();
+                    changed = true;
+                }
+                updated.push(row);
+            }
+
+            if changed {
+                let tmp = temp_path(&record);
+                crate::wheel::write_record_rows(&tmp, &updated)?;
+                std::fs::rename(&tmp, &record)?;
+                rewritten.push(record);
+            }
+        }
+
+        Ok(rewritten)
+    }
+
+    /// Installs whichever of the `seed-packages` aren't already installed, and
+    /// (into a new venv) the project's `requirements` file. A failure is only a
+    /// warning, since the venv itself is still usable.
+    fn seed(&self, created: bool) {
+        let installed: Vec<PackageName> = self
+            .installed_distributions()
+            .iter()
+            .filter_map(|d| PackageName::try_from(d.name.as_str()).ok())
+            .collect();
 
-        if !self.paths.pyvenv_cfg.exists() {
-            let mut pyvenv_file = File::create(&self.paths.pyvenv_cfg)?;
-            pyvenv_file.write_all(self.paths.pyvenv_cfg_content(&python).as_ref())?;
+        let missing: Vec<&str> = self
+            .seed_packages
+            .iter()
+            .map(String::as_str)
+            .filter(
+                |requirement| match requirement_name(requirement).try_into() {
+                    Ok(name) => !installed.contains(&name),
+                    Err(_) => true,
+                },
+            )
+            .collect();
+
+        let requirements = match (&self.project, &self.requirements) {
+            (Some(project), Some(requirements)) if created => {
+                let path = project.join(requirements);
+                if !path.exists() {
+                    eprintln!(
+                        "Warning: The requirements file {} does not exist",
+                        path.to_string_lossy()
+                    );
+                }
+                Some(path).filter(|path| path.exists())
+            }
+            _ => None,
+        };
+        let requirements = requirements.map(|path| path.to_string_lossy().to_string());
+
+        if missing.is_empty() && requirements.is_none() {
+            return;
         }
 
-        self.ensure_pip()?;
+        let mut args = vec!["install"];
+        args.extend(&missing);
+        let mut installing = missing.clone();
+        if let Some(requirements) = &requirements {
+            args.extend(["-r", requirements.as_str()]);
+            installing.push(requirements);
+        }
+        eprintln!("Installing seed packages: {}", installing.join(", "));
+        match self.pip(&args) {
+            Ok(_) => {
+                if let Err(e) = self.run_hook(Hook::PostInstall) {
+                    eprintln!("{e}");
+                }
+            }
+            Err(e) => eprintln!("Failed to install seed packages: {e}"),
+        }
+    }
 
+    /// Runs the hook's configured command (if any) with `sh`, in the context of
+    /// the venv (as with `prp exec`), from the project directory.
+    pub fn run_hook(&self, hook: Hook) -> anyhow::Result<()> {
+        let Some(command) = self.hooks.get(hook) else {
+            return Ok(());
+        };
+
+        let dir = match &self.project {
+            Some(project) => project.clone(),
+            None => std::env::current_dir()?,
+        };
+
+        // Output must not reach stdout, which the shell integration evaluates.
+        let stderr = os_pipe::dup_stderr()?;
+        let status = Command::new("sh")
+            .args(["-c", command])
+            .current_dir(dir)
+            .env("VIRTUAL_ENV", &self.paths.path)
+            .env("PATH", Shell::extend_path(&self.paths.scripts_path))
+            .stdout(stderr)
+            .status()?;
+
+        if !status.success() {
+            anyhow::bail!("The `{}` hook failed ({status})", hook.key());
+        }
         Ok(())
     }
 
-    fn ensure_pip(&self) -> anyhow::Result<()> {
-        Command::new(&self.paths.python_path)
+    /// Installs `source`'s exact set of packages into this venv (including
+    /// editable installs), returning the requirements which couldn't be
+    /// installed, i.e. those unavailable for this venv's python.
+    pub fn clone_packages(&self, source: &Venv) -> anyhow::Result<Vec<String>> {
+        let distributions: Vec<Distribution> = source
+            .installed_distributions()
+            .into_iter()
+            .filter(|d| !d.name.eq_ignore_ascii_case("pip"))
+            .collect();
+        if distributions.is_empty() {
+            return Ok(vec![]);
+        }
+
+        if !self.paths.pip_path.exists() {
+            anyhow::bail!("{} has no pip", self.paths.path.to_string_lossy());
+        }
+        Ok(self.reinstall(&distributions))
+    }
+
+    /// Installs the exact given distributions, returning the requirements
+    /// for any which couldn't be installed.
+    pub fn reinstall(&self, distributions: &[Distribution]) -> Vec<String> {
+        // Wheels prp installed itself are still in the wheel cache, and may
+        // not be anywhere pip can find them; though only those built for this
+        // venv's python are usable, which may not be the one they were
+        // installed for (i.e. when repairing, or cloning to another python).
+        let python = Python::detect(&self.paths.python_path).ok();
+        let mut failed = vec![];
+        let mut requirements: Vec<Vec<String>> = vec![];
+        for distribution in distributions {
+            let cached = python.as_ref().and_then(|python| {
+                self.wheel_cache
+                    .get(&distribution.name, &distribution.version, python)
+            });
+            match cached {
+                Some((wheel, unpacked)) => {
+                    if self.install_unpacked(&wheel, &unpacked, true).is_err() {
+                        failed.push(format!("{}=={}", distribution.name, distribution.version));
+                    }
+                }
+                None => requirements.push(distribution.pip_args()),
+            }
+        }
+        if requirements.is_empty() {
+            return failed;
+        }
+
+        let mut args = vec!["install"];
+        args.extend(requirements.iter().flatten().map(String::as_str));
+        if self.pip(&args).is_ok() {
+            return failed;
+        }
+
+        // Something couldn't be installed, retry individually to find out what.
+        failed.extend(
+            requirements
+                .into_iter()
+                .filter(|requirement| {
+                    let mut args = vec!["install"];
+                    args.extend(requirement.iter().map(String::as_str));
+                    self.pip(&args).is_err()
+                })
+                .map(|requirement| requirement.join(" ")),
+        );
+        failed
+    }
+
+    pub fn installed_distributions(&self) -> Vec<Distribution> {
+        self.paths
+            .site_packages_paths()
+            .iter()
+            .flat_map(|path| crate::distribution::installed(path))
+            .collect()
+    }
+
+    fn ensure_pip(&self, python: &Python) -> anyhow::Result<()> {
+        // i.e. `--fix`ing a venv leaves whichever pip it already has.
+        let has_pip = self
+            .installed_distributions()
+            .iter()
+            .any(|d| d.name.eq_ignore_ascii_case("pip"));
+        if has_pip && self.paths.pip_path.exists() {
+            return Ok(());
+        }
+
+        if self.seed_pip(python)? {
+            return Ok(());
+        }
+
+        // ensurepip installs the pip bundled with python, without the network.
+        let output = Command::new(&self.paths.python_path)
             .args(["-m", "ensurepip", "--upgrade", "--default-pip"])
             .env("VIRTUAL_ENV", &self.paths.path)
             .output()?;
-
-        self.pip(&["install", "--upgrade", "pip"])?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Failed to install pip with ensurepip: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
         Ok(())
     }
 
+    /// Installs pip directly from a wheel (the configured `pip-wheel`, the
+    /// wheel cache, or one bundled with the python installation), avoiding
+    /// both `ensurepip` and the network. Returns whether a wheel was found.
+    fn seed_pip(&self, python: &Python) -> anyhow::Result<bool> {
+        if let Some(path) = self.pip_wheel.as_ref().filter(|p| p.exists()) {
+            self.install_wheel(path, false)?;
+            return Ok(true);
+        }
+
+        if let Some((wheel, unpacked)) = self.wheel_cache.find("pip", python) {
+            self.install_unpacked(&wheel, &unpacked, false)?;
+            return Ok(true);
+        }
+
+        let mut bundled_dirs = vec![PathBuf::from("/usr/share/python-wheels")];
+        if let Some(prefix) = std::fs::canonicalize(&python.exe_path)?
+            .parent()
+            .and_then(Path::parent)
+        {
+            bundled_dirs.push(prefix.join(format!(
+                "lib/python{major}.{minor}/ensurepip/_bundled",
+                major = python.major,
+                minor = python.minor
+            )));
+        }
+
+        if let Some(wheel) = crate::wheel::find_newest(&bundled_dirs, "pip", python) {
+            self.install_wheel(&wheel.path, false)?;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// Records that the venv was just used, for `prp venv gc`. Failing to is
+    /// only worth a warning, not failing whatever used the venv.
+    pub fn mark_used(&self) {
+        if !self.exists() {
+            return;
+        }
+        if let Err(e) = std::fs::File::create(&self.paths.last_used) {
+            eprintln!(
+                "Warning: Failed to record the use of {}: {e}",
+                self.paths.path.to_string_lossy()
+            );
+        }
+    }
+
     pub fn delete(&mut self) -> anyhow::Result<()> {
         Ok(std::fs::remove_dir_all(&self.paths.path)?)
     }
@@ -209,8 +969,65 @@ impl Venv {
         }
     }
 
-    pub fn install(&self, package_spec: PackageSpecifier) -> anyhow::Result<()> {
-        self.pip(&["install", &format!("{}", package_spec)])?;
+    pub fn install(&self, package_spec: PackageSpecifier, compile: bool) -> anyhow::Result<()> {
+        match package_spec {
+            PackageSpecifier::LocalPackage(path) => self.install_wheel(&path, compile),
+            _ => {
+                let spec = format!("{}", package_spec);
+                let mut args = vec!["install", &spec];
+                if !compile {
+                    args.push("--no-compile");
+                }
+                self.pip(&args)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Natively installs a wheel, by way of the wheel cache, and then its
+    /// dependencies with pip.
+    pub fn install_wheel(&self, path: &Path, compile: bool) -> anyhow::Result<()> {
+        let wheel = Wheel::from_path(path)?;
+        let unpacked = self.wheel_cache.unpack(&wheel)?;
+        self.install_unpacked(&wheel, &unpacked, compile)?;
+
+        let requirements = crate::wheel::requires_dist(&unpacked.join(wheel.dist_info_dir()))?;
+        if requirements.is_empty() {
+            return Ok(());
+        }
+
+        if !self.paths.pip_path.exists() {
+            anyhow::bail!(
+                "{} has no pip, to install the dependencies of {}",
+                self.paths.path.to_string_lossy(),
+                wheel.name
+            );
+        }
+
+        let mut args = vec!["install"];
+        args.extend(requirements.iter().map(String::as_str));
+        if !compile {
+            args.push("--no-compile");
+        }
+        self.pip(&args)?;
+        Ok(())
+    }
+
+    fn install_unpacked(
+        &self,
+        wheel: &Wheel,
+        unpacked: &Path,
+        compile: bool,
+    ) -> anyhow::Result<()> {
+        let python = Python::detect(&self.paths.python_path)?;
+        crate::wheel::install(
+            wheel,
+            unpacked,
+            &self.paths,
+            &python,
+            self.link_mode,
+            compile,
+        )?;
         Ok(())
     }
 
@@ -221,15 +1038,58 @@ impl Venv {
             "does not exist"
         };
 
+        let health = if self.paths.path.exists() {
+            let health = self.health();
+            if health.is_healthy() {
+                "ok".to_string()
+            } else {
+                format!("broken (repair with `venv --fix`)\n{health}")
+            }
+        } else {
+            "-".to_string()
+        };
+
+        let health = match self.moved_from() {
+            Some(from) => format!(
+                "moved from {} (relocate with `venv --fix`)",
+                from.to_string_lossy()
+            ),
+            None => health,
+        };
+
+        let cfg = self.cfg();
+        let executable = match cfg.executable() {
+            Some(executable) if executable.exists() => {
+                format!("{} (exists)", executable.to_string_lossy())
+            }
+            Some(executable) => format!("{} (missing)", executable.to_string_lossy()),
+            None => "-".to_string(),
+        };
+
         indoc::eprintdoc!(
             r#"
             Venv Path: {venv} ({exists})
             Venv Name: {name}
             Activated: {activated}
+            Health: {health}
+            Python Version: {version}
+            Python Executable: {executable}
+            Python Home: {home}
+            Prompt: {prompt}
+            System Site Packages: {system_site_packages}
             "#,
             venv = self.paths.path.to_string_lossy(),
             exists = exists,
             name = self.name,
+            health = health,
+            version = cfg.version().unwrap_or_else(|| "-".to_string()),
+            executable = executable,
+            home = cfg
+                .home()
+                .map(|h| h.to_string_lossy().to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            prompt = cfg.prompt().unwrap_or_else(|| "-".to_string()),
+            system_site_packages = cfg.include_system_site_packages(),
             activated = std::env::var_os("VIRTUAL_ENV")
                 .map(|e| e == self.paths.path)
                 .unwrap_or(false)
@@ -238,24 +1098,31 @@ impl Venv {
     }
 }
 
-fn find_project_root(path: &Path, project_root: &[String]) -> PathBuf {
-    for p in path.ancestors() {
-        for root in project_root {
-            if p.join(root).exists() {
-                return p.to_path_buf();
-            }
-        }
+pub struct VenvHealth {
+    pub issues: Vec<String>,
+}
+
+impl VenvHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl std::fmt::Display for VenvHealth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let issues: Vec<String> = self.issues.iter().map(|i| format!("  * {i}")).collect();
+        write!(f, "{}", issues.join("\n"))
     }
-    path.to_path_buf()
 }
 
 fn create_symlink(source: &Path, dest: &Path) -> anyhow::Result<()> {
     let real_source = std::fs::canonicalize(source)?;
 
-    if dest.exists() {
-        let real_path = std::fs::canonicalize(dest)?;
-        if real_path == source {
-            return Ok(());
+    if dest.symlink_metadata().is_ok() {
+        if let Ok(real_path) = std::fs::canonicalize(dest) {
+            if real_path == real_source {
+                return Ok(());
+            }
         }
 
         std::fs::remove_file(dest)?;
@@ -264,3 +1131,325 @@ fn create_symlink(source: &Path, dest: &Path) -> anyhow::Result<()> {
     symlink_file(real_source, dest)?;
     Ok(())
 }
+
+fn create_copy(source: &Path, dest: &Path) -> anyhow::Result<()> {
+    let real_source = std::fs::canonicalize(source)?;
+
+    if dest.symlink_metadata().is_ok() {
+        std::fs::remove_file(dest)?;
+    }
+
+    std::fs::copy(real_source, dest)?;
+    Ok(())
+}
+
+/// Where the `central` strategy keeps venvs for `dir`: under the data
+/// directory, mirroring `dir`'s path relative to the home directory.
+fn central_path(settings: &Settings, dir: &Path) -> anyhow::Result<PathBuf> {
+    let corpus: Corpus = builder()
+        .relative_to_home()?
+        .with_root(RootLocation::XDGData)
+        .with_name(&settings.name)
+        .build()?;
+
+    Ok(corpus.path(dir))
+}
+
+/// The root of the git repository containing `dir`, and its checked out branch
+/// (or for a detached HEAD, the abbreviated commit).
+fn git_head(dir: &Path) -> Option<(PathBuf, String)> {
+    let root = dir.ancestors().find(|p| p.join(".git").exists())?;
+    let dot_git = root.join(".git");
+
+    // Worktrees (and submodules) have a `.git` file pointing at the git dir.
+    let git_dir = if dot_git.is_file() {
+        let content = std::fs::read_to_string(&dot_git).ok()?;
+        let git_dir = PathBuf::from(content.trim().strip_prefix("gitdir:")?.trim());
+        root.join(git_dir)
+    } else {
+        dot_git
+    };
+
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    let branch = match head.strip_prefix("ref:") {
+        Some(reference) => reference
+            .trim()
+            .strip_prefix("refs/heads/")
+            .unwrap_or(reference.trim())
+            .to_string(),
+        None => head.chars().take(12).collect(),
+    };
+    Some((root.to_path_buf(), branch))
+}
+
+/// The venv path given by the `template` strategy's `venv-path`, with `~`
+/// expanded and its `{project}`, `{hash}`, `{python}` and `{name}` placeholders
+/// filled in.
+fn render_template(
+    template: &str,
+    dir: &Path,
+    name: &str,
+    python: &PythonRequest,
+    python_search_path: &[PathBuf],
+) -> anyhow::Result<PathBuf> {
+    let mut path = template.to_string();
+    if let Some(rest) = path.strip_prefix("~/") {
+        let home = std::env::var("HOME")?;
+        path = format!("{home}/{rest}");
+    }
+
+    let project = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path = path
+        .replace("{project}", &project)
+        .replace("{hash}", &short_hash(dir))
+        .replace("{name}", name);
+
+    // Resolving the interpreter means executing them, so only when needed.
+    if path.contains("{python}") {
+        let python = find_python(python, python_search_path)?;
+        path = path.replace("{python}", &format!("{}.{}", python.major, python.minor));
+    }
+
+    Ok(PathBuf::from(path))
+}
+
+/// A short hash of the path which is stable across runs (and prp versions), so
+/// that it can key a venv's location.
+fn short_hash(path: &Path) -> String {
+    // FNV-1a
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in path.to_string_lossy().bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:08x}", hash as u32)
+}
+
+/// A venv found by `find_venvs`.
+pub struct StoredVenv {
+    pub path: PathBuf,
+    pub project: Option<PathBuf>,
+    pub python: Option<String>,
+    pub last_used: Option<SystemTime>,
+}
+
+impl StoredVenv {
+    pub fn read(path: &Path) -> Self {
+        let paths = VenvPaths::new(path);
+        let cfg = paths.read_cfg();
+
+        // Venvs from before prp tracked use were last used when created, at best.
+        let last_used = [&paths.last_used, &paths.pyvenv_cfg]
+            .iter()
+            .find_map(|p| p.metadata().and_then(|m| m.modified()).ok());
+
+        Self {
+            path: path.to_path_buf(),
+            project: cfg.get("prp-project").map(PathBuf::from),
+            python: cfg.version(),
+            last_used,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Whether the project the venv was created for has since been removed.
+    pub fn is_orphaned(&self) -> bool {
+        self.project.as_ref().map(|p| !p.exists()).unwrap_or(false)
+    }
+
+    /// The total size of the venv's files, in bytes.
+    pub fn size(&self) -> u64 {
+        dir_size(&self.path)
+    }
+}
+
+/// Every venv under `root`, skipping the `exclude`d directories.
+pub fn find_venvs(root: &Path, exclude: &[PathBuf]) -> Vec<StoredVenv> {
+    let mut venvs = vec![];
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        if exclude.contains(&dir) {
+            continue;
+        }
+
+        if dir.join("pyvenv.cfg").is_file() {
+            venvs.push(StoredVenv::read(&dir));
+            continue;
+        }
+
+        let Ok(entries) = dir.read_dir() else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                dirs.push(entry.path());
+            }
+        }
+    }
+
+    venvs.sort_by(|a, b| a.path.cmp(&b.path));
+    venvs
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = path.read_dir() else {
+        return 0;
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&entry.path()),
+            Ok(t) if t.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// The directories which were actually moved, given that a venv was moved from
+/// `from` to `to`; i.e. `/a/project/.venv` to `/b/project/.venv` implies the
+/// project moved from `/a/project` to `/b/project` (but not that the rest of
+/// `/a` did). A venv which was itself renamed is all that's known to have moved.
+fn moved_roots(from: &Path, to: &Path) -> (String, String) {
+    let (from, to) = match (from.parent(), to.parent()) {
+        // Never the filesystem root, which is a prefix of every path.
+        (Some(from_parent), Some(to_parent))
+            if from.file_name() == to.file_name()
+                && from_parent.file_name().is_some()
+                && to_parent.file_name().is_some() =>
+        {
+            (from_parent, to_parent)
+        }
+        _ => (from, to),
+    };
+    (
+        from.to_string_lossy().to_string(),
+        to.to_string_lossy().to_string(),
+    )
+}
+
+/// Replaces `path` with a new file holding `content` (and the same
+/// permissions), rather than writing to it in place; it may be a hardlink into
+/// the wheel cache, shared with other venvs.
+fn replace_file(path: &Path, content: &[u8]) -> anyhow::Result<()> {
+    let permissions = std::fs::metadata(path)?.permissions();
+    let tmp = temp_path(path);
+    std::fs::write(&tmp, content)?;
+    std::fs::set_permissions(&tmp, permissions)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// A path beside `path` to write its replacement to.
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}.{}.tmp", std::process::id()))
+}
+
+/// Replaces occurrences of the path `old` (and paths beneath it) in `content`
+/// with `new`. Returns `None` if there were none.
+fn replace_path(content: &str, old: &str, new: &str) -> Option<String> {
+    let mut result = String::with_capacity(content.len());
+    let mut rest = content;
+    let mut replaced = false;
+    while let Some(index) = rest.find(old) {
+        let after = &rest[index + old.len()..];
+
+        // i.e. `/a/project` must not match `/a/project2`.
+        let is_whole = !after.starts_with(|c: char| c.is_alphanumeric() || "-_.".contains(c));
+        result.push_str(&rest[..index]);
+        result.push_str(if is_whole { new } else { old });
+        replaced |= is_whole;
+        rest = after;
+    }
+    result.push_str(rest);
+    replaced.then_some(result)
+}
+
+/// The name of the distribution a requirement is for, i.e. `ruff` for
+/// `ruff>=0.1` or `ruff @ https://...`.
+fn requirement_name(requirement: &str) -> &str {
+    let end = requirement
+        .find(|c: char| !(c.is_alphanumeric() || "-_.".contains(c)))
+        .unwrap_or(requirement.len());
+    &requirement[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roots(from: &str, to: &str) -> (String, String) {
+        moved_roots(Path::new(from), Path::new(to))
+    }
+
+    #[test]
+    fn moved_roots_are_the_project_directories() {
+        assert_eq!(
+            roots("/a/project/.venv", "/b/project/.venv"),
+            ("/a/project".to_string(), "/b/project".to_string())
+        );
+        assert_eq!(
+            roots("/a/x/project/.venv", "/b/x/project/.venv"),
+            ("/a/x/project".to_string(), "/b/x/project".to_string())
+        );
+        assert_eq!(
+            roots("/a/project/.venv", "/a/renamed/.venv"),
+            ("/a/project".to_string(), "/a/renamed".to_string())
+        );
+    }
+
+    #[test]
+    fn moved_roots_of_a_renamed_venv_are_the_venvs() {
+        assert_eq!(
+            roots("/a/project/.venv", "/a/project/venv"),
+            (
+                "/a/project/.venv".to_string(),
+                "/a/project/venv".to_string()
+            )
+        );
+        assert_eq!(
+            roots("/.venv", "/b/.venv"),
+            ("/.venv".to_string(), "/b/.venv".to_string())
+        );
+    }
+
+    #[test]
+    fn replace_path_only_replaces_whole_components() {
+        assert_eq!(
+            replace_path("#!/a/project/.venv/bin/python", "/a/project", "/b/project").as_deref(),
+            Some("#!/b/project/.venv/bin/python")
+        );
+        assert_eq!(
+            replace_path("/a/project2/src", "/a/project", "/b/project"),
+            None
+        );
+    }
+
+    #[test]
+    fn requirement_name_strips_specifiers_extras_and_urls() {
+        assert_eq!(requirement_name("ruff"), "ruff");
+        assert_eq!(requirement_name("ruff>=0.1"), "ruff");
+        assert_eq!(requirement_name("Foo_Bar[extra]"), "Foo_Bar");
+        assert_eq!(requirement_name("pkg @ https://example.com/pkg.whl"), "pkg");
+        assert_eq!(requirement_name("pkg@https://example.com/pkg.whl"), "pkg");
+    }
+
+    #[test]
+    fn requirement_names_compare_normalized() {
+        let name = |value| PackageName::try_from(requirement_name(value)).unwrap();
+        assert_eq!(name("Foo_Bar[extra]"), name("foo-bar"));
+        assert_eq!(name("foo.bar>=1"), name("FOO_BAR"));
+        assert_ne!(name("ruff>=0.1"), name("ruff-lsp"));
+    }
+}
@@ -0,0 +1,861 @@
+use anyhow::Context;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::distribution::Distribution;
+use crate::posy::package_name::PackageName;
+use crate::python::Python;
+use crate::settings::LinkMode;
+use crate::venv::VenvPaths;
+
+/// A `.whl` file on disk, identified by its (PEP 427) file name.
+pub struct Wheel {
+    pub path: PathBuf,
+    pub name: String,
+    pub version: String,
+
+    /// The compatibility tags of the wheel's file name, i.e. `cp311`,
+    /// `abi3`, and `manylinux_2_17_x86_64` (each possibly `.`-separated sets).
+    pub python_tag: String,
+    pub abi_tag: String,
+    pub platform_tag: String,
+}
+
+impl Wheel {
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        if path.extension().map(|e| e != "whl").unwrap_or(true) {
+            anyhow::bail!("{path:?} is not a wheel");
+        }
+
+        let stem = path
+            .file_stem()
+            .context(format!("{path:?} has no file name"))?
+            .to_string_lossy();
+
+        // {name}-{version}(-{build})?-{python}-{abi}-{platform}
+        let parts: Vec<&str> = stem.split('-').collect();
+        if parts.len() < 5 || parts.len() > 6 {
+            anyhow::bail!("{path:?} is not a valid wheel file name");
+        }
+
+        let [python_tag, abi_tag, platform_tag] = &parts[parts.len() - 3..] else {
+            unreachable!("at least 5 parts");
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            name: parts[0].to_string(),
+            version: parts[1].to_string(),
+            python_tag: python_tag.to_string(),
+            abi_tag: abi_tag.to_string(),
+            platform_tag: platform_tag.to_string(),
+        })
+    }
+
+    pub fn stem(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn dist_info_dir(&self) -> String {
+        format!("{}-{}.dist-info", self.name, self.version)
+    }
+
+    pub fn data_dir(&self) -> String {
+        format!("{}-{}.data", self.name, self.version)
+    }
+
+    /// Whether the wheel's tags allow it to be installed for `python`. This is
+    /// looser than pip about platforms; i.e. any `manylinux` tag for the right
+    /// architecture is accepted, whatever its glibc version.
+    pub fn is_compatible(&self, python: &Python) -> bool {
+        let (major, minor) = (&python.major, &python.minor);
+        let interpreter = match python.implementation.as_str() {
+            "cpython" => "cp",
+            "pypy" => "pp",
+            _ => "py",
+        };
+        let minor_number: u64 = minor.parse().unwrap_or(0);
+        let is_cpython = interpreter == "cp";
+        let is_abi3 = is_cpython && self.abi_tag.split('.').any(|abi| abi == "abi3");
+
+        let python_compatible = self.python_tag.split('.').any(|tag| {
+            // An `abi3` wheel works on any later cpython 3.
+            let abi3_minor = tag
+                .strip_prefix(&format!("cp{major}"))
+                .and_then(|m| m.parse::<u64>().ok());
+            tag == format!("py{major}")
+                || tag == format!("py{major}{minor}")
+                || tag == format!("{interpreter}{major}{minor}")
+                || (is_abi3 && abi3_minor.map(|m| m <= minor_number).unwrap_or(false))
+        });
+
+        let abi_compatible = self.abi_tag.split('.').any(|abi| {
+            abi == "none"
+                || (is_cpython && abi == "abi3")
+                || abi.starts_with(&format!("{interpreter}{major}{minor}"))
+                || abi.starts_with(&format!("pypy{major}{minor}"))
+        });
+
+        let platform_compatible = self.platform_tag.split('.').any(|platform| {
+            if platform == "any" {
+                return true;
+            }
+            let os_compatible = match std::env::consts::OS {
+                "linux" => ["linux", "manylinux", "musllinux"]
+                    .iter()
+                    .any(|prefix| platform.starts_with(prefix)),
+                "macos" => platform.starts_with("macosx"),
+                os => platform.starts_with(os),
+            };
+            os_compatible && (platform.ends_with(&python.arch) || platform.ends_with("universal2"))
+        });
+
+        python_compatible && abi_compatible && platform_compatible
+    }
+
+    /// Whether the wheel's `Requires-Python` (read from the wheel file itself)
+    /// is satisfied by `python`; not if it can't be read.
+    fn supports(&self, python: &Python) -> bool {
+        let metadata = File::open(&self.path)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| {
+                let mut archive = zip::ZipArchive::new(file)?;
+                let entry = archive.by_name(&format!("{}/METADATA", self.dist_info_dir()))?;
+                read_headers(BufReader::new(entry), "Requires-Python")
+            });
+        metadata
+            .map(|values| values.iter().all(|v| crate::python::satisfies(v, python)))
+            .unwrap_or(false)
+    }
+}
+
+/// Wheels are unpacked once into the cache, and then linked (or copied) into
+/// each venv they're installed into.
+#[derive(Clone)]
+pub struct WheelCache {
+    pub path: PathBuf,
+}
+
+impl WheelCache {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    pub fn unpack(&self, wheel: &Wheel) -> anyhow::Result<PathBuf> {
+        let dest = self.path.join(wheel.stem());
+        if dest.exists() {
+            return Ok(dest);
+        }
+
+        std::fs::create_dir_all(&self.path)?;
+
+        // Unpack beside the final location, so that a partially unpacked wheel
+        // is never mistaken for a complete one.
+        let tmp = self
+            .path
+            .join(format!(".{}.{}", wheel.stem(), std::process::id()));
+        if tmp.exists() {
+            std::fs::remove_dir_all(&tmp)?;
+        }
+
+        let file = File::open(&wheel.path)?;
+        let mut archive = zip::ZipArchive::new(file)?;
+        archive.extract(&tmp)?;
+
+        if let Err(e) = std::fs::rename(&tmp, &dest) {
+            std::fs::remove_dir_all(&tmp)?;
+            if !dest.exists() {
+                return Err(e.into());
+            }
+        }
+        Ok(dest)
+    }
+
+    /// Every wheel which has already been unpacked into the cache.
+    fn unpacked(&self) -> Vec<(Wheel, PathBuf)> {
+        let Ok(entries) = self.path.read_dir() else {
+            return vec![];
+        };
+        entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .filter_map(|path| {
+                let file_name = path.file_name()?.to_string_lossy().to_string();
+                if file_name.starts_with('.') {
+                    return None;
+                }
+                let wheel = Wheel::from_path(Path::new(&format!("{file_name}.whl"))).ok()?;
+                Some((wheel, path))
+            })
+            .collect()
+    }
+
+    /// The newest already-unpacked wheel for the given distribution name, if
+    /// any, whose `Requires-Python` is satisfied by `python`.
+    pub fn find(&self, name: &str, python: &Python) -> Option<(Wheel, PathBuf)> {
+        self.unpacked()
+            .into_iter()
+            .filter(|(wheel, _)| wheel.name.eq_ignore_ascii_case(name))
+            .filter(|(wheel, path)| unpacked_supports(wheel, path, python))
+            .max_by_key(|(wheel, _)| version_key(&wheel.version))
+    }
+
+    /// The already-unpacked wheel of exactly the given distribution, if any,
+    /// which can be installed for `python`. The distribution's name is as its
+    /// METADATA has it, which a wheel's file name escapes.
+    pub fn get(&self, name: &str, version: &str, python: &Python) -> Option<(Wheel, PathBuf)> {
+        let name = PackageName::try_from(name).ok()?;
+        self.unpacked().into_iter().find(|(wheel, path)| {
+            PackageName::try_from(wheel.name.as_str()).ok().as_ref() == Some(&name)
+                && wheel.version == version
+                && wheel.is_compatible(python)
+                && unpacked_supports(wheel, path, python)
+        })
+    }
+}
+
+/// Whether the `Requires-Python` of a wheel unpacked at `path` is satisfied by
+/// `python`; not if it can't be read.
+fn unpacked_supports(wheel: &Wheel, path: &Path, python: &Python) -> bool {
+    let metadata = path.join(wheel.dist_info_dir()).join("METADATA");
+    metadata_headers(&metadata, "Requires-Python")
+        .map(|values| values.iter().all(|v| crate::python::satisfies(v, python)))
+        .unwrap_or(false)
+}
+
+/// The newest `{name}-*.whl` file in any of the given directories, whose
+/// `Requires-Python` is satisfied by `python`.
+pub fn find_newest(dirs: &[PathBuf], name: &str, python: &Python) -> Option<Wheel> {
+    let mut wheels: Vec<Wheel> = dirs
+        .iter()
+        .filter_map(|dir| dir.read_dir().ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| Wheel::from_path(&entry.path()).ok())
+        .filter(|wheel| wheel.name.eq_ignore_ascii_case(name))
+        .collect();
+    wheels.sort_by_key(|wheel| std::cmp::Reverse(version_key(&wheel.version)));
+    wheels.into_iter().find(|wheel| wheel.supports(python))
+}
+
+/// A rough ordering key for (release segments of) versions, i.e. `23.2.1`.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split('.')
+        .map(|part| {
+            let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().unwrap_or(0)
+        })
+        .collect()
+}
+
+/// Compiles each of the given files (in parallel, across processes), printing
+/// the path of each resulting `.pyc` file. Files which fail to compile are
+/// skipped, same as pip.
+const COMPILE_SCRIPT: &str = r#"
+import multiprocessing
+import py_compile
+import sys
+from concurrent.futures import ProcessPoolExecutor
+
+
+def compile(path):
+    try:
+        return py_compile.compile(path, doraise=True)
+    except Exception:
+        return None
+
+
+paths = sys.stdin.read().splitlines()
+context = multiprocessing.get_context("fork")
+with ProcessPoolExecutor(mp_context=context) as executor:
+    for result in executor.map(compile, paths, chunksize=16):
+        if result:
+            print(result)
+"#;
+
+/// Installs an unpacked wheel into a venv, returning every file written.
+pub fn install(
+    wheel: &Wheel,
+    unpacked: &Path,
+    paths: &VenvPaths,
+    python: &Python,
+    link_mode: LinkMode,
+    compile: bool,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let site_packages = &paths.site_packages_path(python);
+    let dist_info = site_packages.join(wheel.dist_info_dir());
+    let data_dir = wheel.data_dir();
+    let record_name = Path::new(&wheel.dist_info_dir()).join("RECORD");
+
+    uninstall_existing(wheel, paths, site_packages)?;
+
+    let mut installed = vec![];
+    // Where each file installed unmodified came from, so its hash still applies.
+    let mut unmodified = HashMap::new();
+    for relative in walk(unpacked)? {
+        if relative == record_name {
+            continue;
+        }
+
+        let source = unpacked.join(&relative);
+        let mut components = relative.components();
+        let first = components.next().map(|c| c.as_os_str().to_string_lossy());
+
+        if first.as_deref() == Some(data_dir.as_str()) {
+            let scheme = components.next().map(|c| c.as_os_str().to_string_lossy());
+            let rest = components.as_path();
+            let dest = match scheme.as_deref() {
+                Some("purelib") | Some("platlib") => site_packages.join(rest),
+                Some("headers") => paths.include_path.join(&wheel.name).join(rest),
+                Some("data") => paths.path.join(rest),
+                Some("scripts") => {
+                    let dest = paths.scripts_path.join(rest);
+                    let content = std::fs::read(&source)?;
+                    match rewrite_shebang(&content, &paths.python_path) {
+                        Some(rewritten) => write_script(&dest, &rewritten)?,
+                        None => {
+                            write_script(&dest, &content)?;
+                            unmodified.insert(dest.clone(), relative.clone());
+                        }
+                    }
+                    installed.push(dest);
+                    continue;
+                }
+                _ => anyhow::bail!("Unrecognized wheel data path {relative:?}"),
+            };
+            link_file(&source, &dest, link_mode)?;
+            unmodified.insert(dest.clone(), relative.clone());
+            installed.push(dest);
+        } else {
+            let dest = site_packages.join(&relative);
+            link_file(&source, &dest, link_mode)?;
+            unmodified.insert(dest.clone(), relative.clone());
+            installed.push(dest);
+        }
+    }
+
+    let entry_points = dist_info.join("entry_points.txt");
+    for (name, module, function) in read_entry_points(&entry_points)? {
+        let dest = paths.script(&versioned_script_name(wheel, &name, python));
+        write_script(
+            &dest,
+            &entry_point_script(&paths.python_path, &module, &function),
+        )?;
+        installed.push(dest);
+    }
+
+    if compile {
+        let sources: Vec<PathBuf> = installed
+            .iter()
+            .filter(|p| p.starts_with(site_packages))
+            .filter(|p| p.extension().map(|e| e == "py").unwrap_or(false))
+            .cloned()
+            .collect();
+        installed.extend(compile_bytecode(&paths.python_path, &sources)?);
+    }
+
+    let installer = dist_info.join("INSTALLER");
+    std::fs::write(&installer, "prp\n")?;
+    installed.push(installer);
+
+    write_record(
+        &unpacked.join(&record_name),
+        &dist_info.join("RECORD"),
+        site_packages,
+        &installed,
+        &unmodified,
+    )?;
+
+    Ok(installed)
+}
+
+/// Removes any installed distribution of the same name as `wheel` (i.e. an
+/// older version), by the files its `RECORD` lists, as pip does. Directories
+/// left empty within site-packages are removed too.
+fn uninstall_existing(
+    wheel: &Wheel,
+    paths: &VenvPaths,
+    site_packages: &Path,
+) -> anyhow::Result<()> {
+    let Ok(name) = PackageName::try_from(wheel.name.as_str()) else {
+        return Ok(());
+    };
+    let Ok(entries) = site_packages.read_dir() else {
+        return Ok(());
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let dist_info = entry.path();
+        if dist_info
+            .extension()
+            .map(|e| e != "dist-info")
+            .unwrap_or(true)
+        {
+            continue;
+        }
+        let Some(distribution) = Distribution::from_dist_info(&dist_info) else {
+            continue;
+        };
+        if PackageName::try_from(distribution.name.as_str())
+            .ok()
+            .as_ref()
+            != Some(&name)
+        {
+            continue;
+        }
+
+        let record = dist_info.join("RECORD");
+        let rows = if record.exists() {
+            read_record(&record)?
+        } else {
+            vec![]
+        };
+
+        let mut dirs = vec![];
+        for row in rows {
+            // Never anything outside of the venv, whatever the RECORD says.
+            let path = normalize_path(&site_packages.join(&row.path));
+            if !path.starts_with(&paths.path) || path.symlink_metadata().is_err() {
+                continue;
+            }
+            std::fs::remove_file(&path)?;
+            dirs.extend(path.ancestors().skip(1).map(Path::to_path_buf));
+        }
+
+        // Deepest first, so that a parent is only tried once it's emptied.
+        dirs.retain(|dir| dir.starts_with(site_packages) && dir != site_packages);
+        dirs.sort();
+        dirs.dedup();
+        dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+        for dir in dirs {
+            let is_empty = dir
+                .read_dir()
+                .map(|mut entries| entries.next().is_none())
+                .unwrap_or(false);
+            if is_empty {
+                std::fs::remove_dir(&dir)?;
+            }
+        }
+
+        if dist_info.exists() {
+            std::fs::remove_dir_all(&dist_info)?;
+        }
+    }
+    Ok(())
+}
+
+/// `path`, with any `.` and `..` components resolved (lexically, without
+/// following symlinks).
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            component => result.push(component),
+        }
+    }
+    result
+}
+
+/// Places `source` at `dest` according to `link_mode`, falling back to a copy
+/// whenever linking isn't possible (e.g. across filesystems).
+pub fn link_file(source: &Path, dest: &Path, link_mode: LinkMode) -> anyhow::Result<()> {
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if dest.symlink_metadata().is_ok() {
+        std::fs::remove_file(dest)?;
+    }
+
+    match link_mode {
+        LinkMode::Copy => {
+            std::fs::copy(source, dest)?;
+        }
+        LinkMode::Hardlink => {
+            if std::fs::hard_link(source, dest).is_err() {
+                std::fs::copy(source, dest)?;
+            }
+        }
+        LinkMode::Reflink => {
+            reflink_copy::reflink_or_copy(source, dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// Compiles `sources` to bytecode with the venv's interpreter in a single
+/// invocation, returning the `__pycache__` files which were produced.
+pub fn compile_bytecode(python_path: &Path, sources: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    if sources.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut child = Command::new(python_path)
+        .args(["-c", COMPILE_SCRIPT])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    {
+        let mut stdin = child.stdin.take().context("Failed to open stdin")?;
+        for source in sources {
+            writeln!(stdin, "{}", source.to_string_lossy())?;
+        }
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to compile bytecode: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .map(PathBuf::from)
+        .collect())
+}
+
+fn walk(root: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in dir.read_dir()? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path.strip_prefix(root)?.to_path_buf());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The script, with its `#!python` shebang pointed at the venv's interpreter;
+/// `None` if it doesn't have one.
+fn rewrite_shebang(content: &[u8], python_path: &Path) -> Option<Vec<u8>> {
+    if !content.starts_with(b"#!python") {
+        return None;
+    }
+
+    let rest = match content.iter().position(|b| *b == b'\n') {
+        Some(index) => &content[index..],
+        None => &[],
+    };
+
+    let mut result = format!("#!{}", python_path.to_string_lossy()).into_bytes();
+    result.extend_from_slice(rest);
+    Some(result)
+}
+
+fn write_script(dest: &Path, content: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if dest.symlink_metadata().is_ok() {
+        std::fs::remove_file(dest)?;
+    }
+
+    let mut file = File::create(dest)?;
+    file.write_all(content)?;
+    file.set_permissions(std::fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+fn read_entry_points(path: &Path) -> anyhow::Result<Vec<(String, String, String)>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut result = vec![];
+    let mut in_scripts = false;
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            in_scripts = line == "[console_scripts]" || line == "[gui_scripts]";
+            continue;
+        }
+
+        if !in_scripts {
+            continue;
+        }
+
+        if let Some((name, target)) = line.split_once('=') {
+            // Strip any extras, i.e. `foo = foo.cli:main [extra]`.
+            let target = target.split('[').next().unwrap_or("").trim();
+            if let Some((module, function)) = target.split_once(':') {
+                result.push((
+                    name.trim().to_string(),
+                    module.trim().to_string(),
+                    function.trim().to_string(),
+                ));
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// pip and setuptools' wheels name some scripts after the python version
+/// they were built with (i.e. `pip3.12`, `easy_install-3.12`), which pip
+/// renames to the version they're installed for; so do the same.
+fn versioned_script_name(wheel: &Wheel, name: &str, python: &Python) -> String {
+    if !["pip", "setuptools"].contains(&wheel.name.to_lowercase().as_str()) {
+        return name.to_string();
+    }
+
+    let is_version = |v: &str| !v.is_empty() && v.chars().all(|c| c.is_ascii_digit() || c == '.');
+    for prefix in ["pip", "easy_install-"] {
+        let Some(version) = name.strip_prefix(prefix).filter(|v| is_version(v)) else {
+            continue;
+        };
+        return if version.contains('.') {
+            format!("{prefix}{}.{}", python.major, python.minor)
+        } else {
+            format!("{prefix}{}", python.major)
+        };
+    }
+    name.to_string()
+}
+
+fn entry_point_script(python_path: &Path, module: &str, function: &str) -> Vec<u8> {
+    let import_name = function.split('.').next().unwrap_or(function);
+    indoc::formatdoc!(
+        r#"
+        #!{python}
+        # -*- coding: utf-8 -*-
+        import re
+        import sys
+        from {module} import {import_name}
+        if __name__ == "__main__":
+            sys.argv[0] = re.sub(r"(-script\.pyw|\.exe)?$", "", sys.argv[0])
+            sys.exit({function}())
+        "#,
+        python = python_path.to_string_lossy(),
+    )
+    .into_bytes()
+}
+
+/// Rewrites the wheel's `RECORD` in terms of the installed file locations,
+/// preserving the original hashes of the files which were installed
+/// unmodified (keyed by their path within the wheel).
+pub fn write_record(
+    source_record: &Path,
+    dest_record: &Path,
+    site_packages: &Path,
+    installed: &[PathBuf],
+    unmodified: &HashMap<PathBuf, PathBuf>,
+) -> anyhow::Result<()> {
+    let mut hashes = HashMap::new();
+    if source_record.exists() {
+        for row in read_record(source_record)? {
+            hashes.insert(PathBuf::from(row.path), (row.hash, row.size));
+        }
+    }
+
+    let mut rows: Vec<RecordRow> = installed
+        .iter()
+        .map(|path| {
+            let (hash, size) = unmodified
+                .get(path)
+                .and_then(|relative| hashes.get(relative))
+                .cloned()
+                .unwrap_or_default();
+            RecordRow {
+                path: relative_path(path, site_packages)
+                    .to_string_lossy()
+                    .to_string(),
+                hash,
+                size,
+            }
+        })
+        .collect();
+    rows.push(RecordRow {
+        path: relative_path(dest_record, site_packages)
+            .to_string_lossy()
+            .to_string(),
+        hash: String::new(),
+        size: String::new(),
+    });
+    write_record_rows(dest_record, &rows)
+}
+
+/// A line of a `RECORD` file: a path, and its hash and size (which may be empty).
+pub struct RecordRow {
+    pub path: String,
+    pub hash: String,
+    pub size: String,
+}
+
+/// Reads a `RECORD` file, which is CSV (so paths containing commas are quoted).
+pub fn read_record(path: &Path) -> anyhow::Result<Vec<RecordRow>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)?;
+
+    let mut rows = vec![];
+    for record in reader.records() {
+        let record = record?;
+        let field = |index| record.get(index).unwrap_or("").to_string();
+        rows.push(RecordRow {
+            path: field(0),
+            hash: field(1),
+            size: field(2),
+        });
+    }
+    Ok(rows)
+}
+
+pub fn write_record_rows(path: &Path, rows: &[RecordRow]) -> anyhow::Result<()> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_path(path)?;
+    for row in rows {
+        writer.write_record([&row.path, &row.hash, &row.size])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// The requirements in a wheel's `METADATA` (its `Requires-Dist` headers),
+/// other than those only needed for extras.
+pub fn requires_dist(dist_info: &Path) -> anyhow::Result<Vec<String>> {
+    let requirements = metadata_headers(&dist_info.join("METADATA"), "Requires-Dist")?;
+    Ok(requirements
+        .into_iter()
+        .filter(|requirement| {
+            !requirement
+                .split_once(';')
+                .map(|(_, marker)| marker.contains("extra"))
+                .unwrap_or(false)
+        })
+        .collect())
+}
+
+/// The values of every `name` header in a `METADATA` file (if it exists).
+pub(crate) fn metadata_headers(metadata: &Path, name: &str) -> anyhow::Result<Vec<String>> {
+    if !metadata.exists() {
+        return Ok(vec![]);
+    }
+    read_headers(BufReader::new(File::open(metadata)?), name)
+}
+
+fn read_headers(metadata: impl BufRead, name: &str) -> anyhow::Result<Vec<String>> {
+    let mut values = vec![];
+    for line in metadata.lines() {
+        let line = line?;
+
+        // Headers end at the first blank line, after which is the description.
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((header, value)) = line.split_once(':') {
+            if header.eq_ignore_ascii_case(name) {
+                values.push(value.trim().to_string());
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// `path`, relative to `base` (introducing `..` components as necessary).
+pub fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path_components: Vec<Component> = path.components().collect();
+    let base_components: Vec<Component> = base.components().collect();
+
+    let common = path_components
+        .iter()
+        .zip(base_components.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut result = PathBuf::new();
+    for _ in common..base_components.len() {
+        result.push("..");
+    }
+    for component in &path_components[common..] {
+        result.push(component);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn python(implementation: &str, version: &str) -> Python {
+        let mut parts = version.split('.').map(String::from);
+        Python {
+            exe_path: PathBuf::from("/usr/bin/python3"),
+            implementation: implementation.to_string(),
+            arch: "x86_64".to_string(),
+            is_venv: false,
+            major: parts.next().unwrap(),
+            minor: parts.next().unwrap(),
+            patch: parts.next().unwrap(),
+        }
+    }
+
+    fn compatible(file_name: &str, python: &Python) -> bool {
+        Wheel::from_path(Path::new(file_name))
+            .unwrap()
+            .is_compatible(python)
+    }
+
+    #[test]
+    fn pure_wheels_are_compatible_by_major_version() {
+        let py311 = python("cpython", "3.11.4");
+        assert!(compatible("demo-1.0-py3-none-any.whl", &py311));
+        assert!(compatible("demo-1.0-py2.py3-none-any.whl", &py311));
+        assert!(!compatible("demo-1.0-py2-none-any.whl", &py311));
+    }
+
+    #[test]
+    fn native_wheels_need_the_same_python_and_platform() {
+        let py311 = python("cpython", "3.11.4");
+        let py312 = python("cpython", "3.12.1");
+        let wheel = "demo-1.0-cp311-cp311-manylinux_2_17_x86_64.manylinux2014_x86_64.whl";
+        if cfg!(target_os = "linux") {
+            assert!(compatible(wheel, &py311));
+        }
+        assert!(!compatible(wheel, &py312));
+        assert!(!compatible(wheel, &python("pypy", "3.11.4")));
+        assert!(!compatible(
+            "demo-1.0-cp311-cp311-manylinux_2_17_aarch64.whl",
+            &py311
+        ));
+    }
+
+    #[test]
+    fn abi3_wheels_work_on_later_pythons() {
+        let wheel = "demo-1.0-cp39-abi3-manylinux_2_17_x86_64.whl";
+        if cfg!(target_os = "linux") {
+            assert!(compatible(wheel, &python("cpython", "3.12.1")));
+        }
+        assert!(!compatible(wheel, &python("cpython", "3.8.18")));
+    }
+}